@@ -13,11 +13,21 @@ use tokio::sync::mpsc;
 use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
 
 mod modbus;
+mod modbus_gateway;
 mod modbus_slave;
+mod modbus_sniffer;
 mod mqtt;
+mod recorder;
+mod script;
+mod serial;
 use modbus::*;
+use modbus_gateway::*;
 use modbus_slave::*;
+use modbus_sniffer::*;
 use mqtt::*;
+use recorder::*;
+use script::*;
+use serial::*;
 
 // Struct để lưu thông tin cổng serial
 #[derive(Debug, Serialize, Clone)]
@@ -26,6 +36,10 @@ pub struct PortInfo {
     pub port_type: String,
     pub manufacturer: Option<String>,
     pub product: Option<String>,
+    pub port_kind: String, // usb, pci, bluetooth, virtual, unknown
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
 }
 
 // Cấu hình serial port
@@ -40,6 +54,18 @@ pub struct SerialConfig {
     pub rts: bool,
     #[serde(default)]
     pub line_ending: Option<String>, // None, CR, LF, CRLF
+    #[serde(default = "default_flow_control")]
+    pub flow_control: String, // none, rts_cts, xon_xoff
+}
+
+// Cấu hình mới cho port đang mở (baud rate, framing)
+#[derive(Debug, Deserialize, Clone)]
+pub struct SerialReconfigureConfig {
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: String,
+    pub parity: String,
 }
 
 // Dữ liệu nhận được từ serial
@@ -58,6 +84,54 @@ pub struct PortDisconnected {
     pub timestamp: u64,
 }
 
+// Event khi port được mở lại tự động sau khi thiết bị cắm lại
+#[derive(Debug, Serialize, Clone)]
+pub struct PortReopened {
+    pub port_name: String,
+    pub previous_port_name: String,
+    pub timestamp: u64,
+}
+
+// Thông tin để mở lại port khi thiết bị (cùng VID/PID/serial) xuất hiện lại
+#[derive(Debug, Clone)]
+pub struct AutoReopenEntry {
+    pub config: SerialConfig,
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+}
+
+impl AutoReopenEntry {
+    fn matches(&self, port: &PortInfo) -> bool {
+        if self.vid.is_none() {
+            // Không có thông tin USB: chỉ so sánh theo tên
+            return port.name == self.config.port_name;
+        }
+        self.vid == port.vid && self.pid == port.pid && self.serial_number == port.serial_number
+    }
+}
+
+// Chu kỳ mặc định quét thay đổi danh sách port (ms)
+const SERIAL_WATCH_INTERVAL_MS: u64 = 1000;
+
+// Trạng thái các đường tín hiệu modem (CTS, DSR, RI, CD)
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SerialSignals {
+    pub port_name: String,
+    pub cts: bool,
+    pub dsr: bool,
+    pub ri: bool,
+    pub cd: bool,
+    pub timestamp: u64,
+}
+
+// Chu kỳ poll tín hiệu modem (ms)
+const SERIAL_SIGNAL_POLL_MS: u64 = 50;
+
+// Thời gian break mặc định và tối đa (ms)
+const SERIAL_BREAK_DEFAULT_MS: u64 = 250;
+const SERIAL_BREAK_MAX_MS: u64 = 5000;
+
 // ===================== TCP STRUCTS =====================
 
 // TCP Client configuration
@@ -151,6 +225,13 @@ pub enum ModbusConnectionConfig {
         unit_id: u8,
         response_timeout_ms: u32,
     },
+    // Frame RTU (có CRC) qua TCP socket, không có MBAP header
+    RtuOverTcp {
+        host: String,
+        port: u16,
+        slave_id: u8,
+        response_timeout_ms: u32,
+    },
 }
 
 /// Modbus connection handle
@@ -164,15 +245,23 @@ pub struct ModbusConnectionHandle {
     pub serial_port: Option<Arc<Mutex<Box<dyn SerialPort>>>>,
     // For TCP: sender channel
     pub tcp_tx: Option<mpsc::Sender<Vec<u8>>>,
-    pub tcp_rx: Option<Arc<Mutex<mpsc::Receiver<Vec<u8>>>>>,
+    // For TCP: requests đang chờ response, theo transaction ID
+    pub tcp_pending: Option<PendingTransactions>,
 }
 
+/// Response channel của từng transaction Modbus TCP đang chờ
+pub type PendingTransactions = Arc<Mutex<HashMap<u16, std::sync::mpsc::Sender<Vec<u8>>>>>;
+
 // ===================== STATE MANAGEMENT =====================
 
 // Quản lý trạng thái serial port
 pub struct SerialState {
     ports: Mutex<HashMap<String, Arc<Mutex<Box<dyn SerialPort>>>>>,
     running: Mutex<HashMap<String, bool>>,
+    configs: Mutex<HashMap<String, SerialConfig>>,
+    auto_reopen: Mutex<HashMap<String, AutoReopenEntry>>,
+    // Cờ chạy của watcher hiện tại (mỗi lần start một cờ riêng)
+    watcher: Mutex<Option<Arc<AtomicBool>>>,
 }
 
 impl Default for SerialState {
@@ -180,6 +269,9 @@ impl Default for SerialState {
         Self {
             ports: Mutex::new(HashMap::new()),
             running: Mutex::new(HashMap::new()),
+            configs: Mutex::new(HashMap::new()),
+            auto_reopen: Mutex::new(HashMap::new()),
+            watcher: Mutex::new(None),
         }
     }
 }
@@ -208,6 +300,7 @@ impl Default for TcpState {
 // Quản lý trạng thái Modbus
 pub struct ModbusState {
     connections: Arc<Mutex<HashMap<String, ModbusConnectionHandle>>>,
+    scans: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
     runtime: tokio::runtime::Runtime,
 }
 
@@ -215,6 +308,7 @@ impl Default for ModbusState {
     fn default() -> Self {
         Self {
             connections: Arc::new(Mutex::new(HashMap::new())),
+            scans: Arc::new(Mutex::new(HashMap::new())),
             runtime: tokio::runtime::Builder::new_multi_thread()
                 .worker_threads(2)
                 .enable_all()
@@ -243,92 +337,349 @@ impl Default for ModbusSlaveState {
     }
 }
 
+// Helper function: parse data bits (5-8)
+fn parse_data_bits(data_bits: u8) -> Result<DataBits, String> {
+    match data_bits {
+        5 => Ok(DataBits::Five),
+        6 => Ok(DataBits::Six),
+        7 => Ok(DataBits::Seven),
+        8 => Ok(DataBits::Eight),
+        _ => Err("Data bits không hợp lệ".to_string()),
+    }
+}
+
+// Helper function: parse stop bits (1, 1.5, 2)
+fn parse_stop_bits(stop_bits: &str) -> Result<StopBits, String> {
+    match stop_bits {
+        "1" => Ok(StopBits::One),
+        "1.5" => Ok(StopBits::Two), // serialport crate không hỗ trợ 1.5, dùng 2
+        "2" => Ok(StopBits::Two),
+        _ => Err("Stop bits không hợp lệ".to_string()),
+    }
+}
+
+// Helper function: parse parity (none, odd, even)
+fn parse_parity(parity: &str) -> Result<Parity, String> {
+    match parity.to_lowercase().as_str() {
+        "none" => Ok(Parity::None),
+        "odd" => Ok(Parity::Odd),
+        "even" => Ok(Parity::Even),
+        _ => Err("Parity không hợp lệ".to_string()),
+    }
+}
+
+// Helper function: gap timeout dựa vào baud rate (dùng khi không có line ending)
+// Công thức: thời gian truyền 256 bytes, min 5ms, max 50ms
+// Cho phép message interval ~50ms+ được tách riêng
+fn gap_timeout_ms(baud: u32) -> u64 {
+    let time_for_256b = (256 * 10 * 1000) / baud.max(1) as u64; // ms để truyền 256 bytes
+    time_for_256b.clamp(5, 50)
+}
+
+// Helper function: parse flow control (none, rts_cts, xon_xoff)
+fn parse_flow_control(flow_control: &str) -> Result<FlowControl, String> {
+    match flow_control.to_lowercase().as_str() {
+        "" | "none" => Ok(FlowControl::None),
+        "rts_cts" => Ok(FlowControl::Hardware),
+        "xon_xoff" => Ok(FlowControl::Software),
+        _ => Err("Flow control không hợp lệ".to_string()),
+    }
+}
+
 // Helper function: tìm vị trí của subsequence trong slice
 fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     haystack.windows(needle.len()).position(|window| window == needle)
 }
 
+// Lọc mặc định: chỉ lấy USB ports với tên hợp lệ theo OS
+fn is_default_listed_port(p: &serialport::SerialPortInfo) -> bool {
+    // Chỉ lấy USB ports
+    if !matches!(p.port_type, serialport::SerialPortType::UsbPort(_)) {
+        return false;
+    }
+
+    let name = &p.port_name;
+
+    // Windows: COMx (COM1, COM2, ...)
+    #[cfg(target_os = "windows")]
+    {
+        name.starts_with("COM")
+    }
+
+    // Linux: /dev/ttyUSB*, /dev/ttyACM*, /dev/ttyS*
+    #[cfg(target_os = "linux")]
+    {
+        name.starts_with("/dev/ttyUSB")
+            || name.starts_with("/dev/ttyACM")
+            || name.starts_with("/dev/ttyS")
+    }
+
+    // macOS: /dev/tty.* (loại trừ usbmodem)
+    #[cfg(target_os = "macos")]
+    {
+        name.starts_with("/dev/tty.")
+    }
+
+    // Fallback cho các OS khác
+    #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
+    {
+        true
+    }
+}
+
+// Tên hiển thị ngắn của port
+fn short_port_name(port_name: &str) -> String {
+    if cfg!(target_os = "windows") {
+        // Windows: giữ nguyên tên (COM1, COM2, ...)
+        port_name.to_string()
+    } else {
+        // Unix-like: lấy phần cuối sau dấu /
+        port_name.rsplit('/').next().unwrap_or(port_name).to_string()
+    }
+}
+
+// Chuyển SerialPortInfo thành PortInfo
+fn port_info_from(p: serialport::SerialPortInfo) -> PortInfo {
+    let short_name = short_port_name(&p.port_name);
+
+    // Lấy thông tin USB nếu có
+    let (port_kind, manufacturer, product, vid, pid, serial_number) = match &p.port_type {
+        serialport::SerialPortType::UsbPort(usb_info) => {
+            #[allow(unused_mut)]
+            let mut prod = usb_info.product.clone();
+            // Windows: loại bỏ hậu tố (COMx) từ product name
+            #[cfg(target_os = "windows")]
+            if let Some(ref p) = prod {
+                if let Some(idx) = p.rfind(" (COM") {
+                    prod = Some(p[..idx].to_string());
+                }
+            }
+            (
+                "usb",
+                usb_info.manufacturer.clone(),
+                prod,
+                Some(usb_info.vid),
+                Some(usb_info.pid),
+                usb_info.serial_number.clone(),
+            )
+        }
+        serialport::SerialPortType::PciPort => ("pci", None, None, None, None, None),
+        serialport::SerialPortType::BluetoothPort => ("bluetooth", None, None, None, None, None),
+        serialport::SerialPortType::Unknown => {
+            let kind = if is_virtual_port_name(&p.port_name) { "virtual" } else { "unknown" };
+            (kind, None, None, None, None, None)
+        }
+    };
+
+    PortInfo {
+        name: p.port_name,
+        port_type: short_name,
+        manufacturer,
+        product,
+        port_kind: port_kind.to_string(),
+        vid,
+        pid,
+        serial_number,
+    }
+}
+
 // Liệt kê các cổng serial có sẵn (hỗ trợ Windows, Linux, macOS)
+// Mặc định chỉ lấy USB ports; options cho phép lấy tất cả, port ảo và lọc theo glob
 #[tauri::command]
-fn list_serial_ports() -> Result<Vec<PortInfo>, String> {
+fn list_serial_ports(options: Option<PortListOptions>) -> Result<Vec<PortInfo>, String> {
+    collect_serial_ports(&options.unwrap_or_default())
+}
+
+fn collect_serial_ports(options: &PortListOptions) -> Result<Vec<PortInfo>, String> {
     let ports = serialport::available_ports().map_err(|e| e.to_string())?;
 
-    let port_list: Vec<PortInfo> = ports
+    let mut port_list: Vec<PortInfo> = ports
         .into_iter()
-        .filter(|p| {
-            // Chỉ lấy USB ports
-            if !matches!(p.port_type, serialport::SerialPortType::UsbPort(_)) {
-                return false;
+        .filter(|p| options.include_all || is_default_listed_port(p))
+        .map(port_info_from)
+        .collect();
+
+    // Port ảo (pty, rfcomm, socat) không được OS enumerate
+    if options.include_virtual {
+        for name in scan_virtual_ports(options.name_glob.as_deref()) {
+            if port_list.iter().any(|p| p.name == name) {
+                continue;
             }
+            port_list.push(PortInfo {
+                port_type: short_port_name(&name),
+                name,
+                manufacturer: None,
+                product: None,
+                port_kind: "virtual".to_string(),
+                vid: None,
+                pid: None,
+                serial_number: None,
+            });
+        }
+    }
 
-            let name = &p.port_name;
+    // Lọc theo glob (full path hoặc tên ngắn)
+    if let Some(ref glob) = options.name_glob {
+        port_list.retain(|p| glob_match(glob, &p.name) || glob_match(glob, &p.port_type));
+    }
 
-            // Windows: COMx (COM1, COM2, ...)
-            #[cfg(target_os = "windows")]
-            {
-                name.starts_with("COM")
-            }
+    Ok(port_list)
+}
 
-            // Linux: /dev/ttyUSB*, /dev/ttyACM*, /dev/ttyS*
-            #[cfg(target_os = "linux")]
-            {
-                name.starts_with("/dev/ttyUSB")
-                    || name.starts_with("/dev/ttyACM")
-                    || name.starts_with("/dev/ttyS")
-            }
+// Bắt đầu theo dõi cắm/rút thiết bị: emit serial-port-added / serial-port-removed
+#[tauri::command]
+fn start_port_watcher(
+    app: AppHandle,
+    state: State<SerialState>,
+    options: Option<PortListOptions>,
+    interval_ms: Option<u64>,
+) -> Result<String, String> {
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let mut watcher = state.watcher.lock();
+        if watcher.is_some() {
+            return Err("Port watcher đã đang chạy".to_string());
+        }
+        *watcher = Some(running.clone());
+    }
 
-            // macOS: /dev/tty.* (loại trừ usbmodem)
-            #[cfg(target_os = "macos")]
-            {
-                name.starts_with("/dev/tty.")
-            }
+    let options = options.unwrap_or_default();
+    let interval = Duration::from_millis(interval_ms.unwrap_or(SERIAL_WATCH_INTERVAL_MS).max(100));
+    let mut known: HashMap<String, PortInfo> = collect_serial_ports(&options)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| (p.name.clone(), p))
+        .collect();
 
-            // Fallback cho các OS khác
-            #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
-            {
-                true
-            }
-        })
-        .map(|p| {
-            let short_name = if cfg!(target_os = "windows") {
-                // Windows: giữ nguyên tên (COM1, COM2, ...)
-                p.port_name.clone()
-            } else {
-                // Unix-like: lấy phần cuối sau dấu /
-                p.port_name
-                    .split('/')
-                    .last()
-                    .unwrap_or(&p.port_name)
-                    .to_string()
+    thread::spawn(move || {
+        // Lần mở lại thất bại theo entry: (số lần thử, thời điểm được thử tiếp)
+        let mut retries: HashMap<String, (u32, Instant)> = HashMap::new();
+
+        while running.load(Ordering::SeqCst) {
+            thread::sleep(interval);
+
+            let Ok(current) = collect_serial_ports(&options) else {
+                continue;
             };
+            let current: HashMap<String, PortInfo> =
+                current.into_iter().map(|p| (p.name.clone(), p)).collect();
 
-            // Lấy thông tin USB nếu có
-            let (manufacturer, product) = match &p.port_type {
-                serialport::SerialPortType::UsbPort(usb_info) => {
-                    #[allow(unused_mut)]
-                    let mut prod = usb_info.product.clone();
-                    // Windows: loại bỏ hậu tố (COMx) từ product name
-                    #[cfg(target_os = "windows")]
-                    if let Some(ref p) = prod {
-                        if let Some(idx) = p.rfind(" (COM") {
-                            prod = Some(p[..idx].to_string());
-                        }
-                    }
-                    (usb_info.manufacturer.clone(), prod)
+            for (name, info) in known.iter() {
+                if !current.contains_key(name) {
+                    let _ = app.emit("serial-port-removed", info.clone());
+                }
+            }
+            for (name, info) in current.iter() {
+                if !known.contains_key(name) {
+                    let _ = app.emit("serial-port-added", info.clone());
+                }
+            }
+
+            // Auto-reopen: mọi entry có thiết bị đang cắm nhưng port chưa mở, kể cả khi thiết bị
+            // reset nhanh hơn 1 chu kỳ quét (tên không biến mất) hoặc lần mở trước bị lỗi
+            let state = app.state::<SerialState>();
+            let entries: Vec<(String, AutoReopenEntry)> = state
+                .auto_reopen
+                .lock()
+                .iter()
+                .map(|(key, e)| (key.clone(), e.clone()))
+                .collect();
+            retries.retain(|key, _| entries.iter().any(|(k, _)| k == key));
+
+            for (previous_name, entry) in entries {
+                if state.ports.lock().contains_key(&previous_name) {
+                    retries.remove(&previous_name);
+                    continue;
+                }
+                if retries.get(&previous_name).is_some_and(|(_, next_try)| Instant::now() < *next_try) {
+                    continue;
+                }
+
+                // Ưu tiên tên cũ, sau đó thiết bị cùng VID/PID/serial
+                let Some(info) = current
+                    .get(&previous_name)
+                    .filter(|p| entry.matches(p))
+                    .or_else(|| current.values().find(|p| entry.matches(p)))
+                else {
+                    continue;
+                };
+                let name = info.name.clone();
+                if state.ports.lock().contains_key(&name) {
+                    continue;
                 }
-                _ => (None, None),
-            };
 
-            PortInfo {
-                name: p.port_name,
-                port_type: short_name,
-                manufacturer,
-                product,
+                let mut config = entry.config.clone();
+                config.port_name = name.clone();
+
+                if open_serial_port(&app, &state, config.clone()).is_ok() {
+                    retries.remove(&previous_name);
+                    {
+                        let mut auto_reopen = state.auto_reopen.lock();
+                        auto_reopen.remove(&previous_name);
+                        auto_reopen.insert(name.clone(), AutoReopenEntry { config, ..entry });
+                    }
+                    let _ = app.emit("serial-port-reopened", PortReopened {
+                        port_name: name,
+                        previous_port_name: previous_name,
+                        timestamp: get_timestamp(),
+                    });
+                } else {
+                    // Backoff: chu kỳ quét x 2^n, tối đa 30 s
+                    let attempts = retries.get(&previous_name).map_or(0, |(n, _)| *n) + 1;
+                    let backoff = (interval * 2u32.pow(attempts.min(6))).min(Duration::from_secs(30));
+                    retries.insert(previous_name, (attempts, Instant::now() + backoff));
+                }
             }
-        })
-        .collect();
 
-    Ok(port_list)
+            known = current;
+        }
+    });
+
+    Ok("Port watcher started".to_string())
+}
+
+// Dừng theo dõi cắm/rút thiết bị
+#[tauri::command]
+fn stop_port_watcher(state: State<SerialState>) -> Result<String, String> {
+    if let Some(running) = state.watcher.lock().take() {
+        running.store(false, Ordering::SeqCst);
+    }
+    Ok("Port watcher stopped".to_string())
+}
+
+// Bật/tắt tự động mở lại port (với cấu hình hiện tại) khi thiết bị được cắm lại
+#[tauri::command]
+fn set_port_auto_reopen(
+    state: State<SerialState>,
+    port_name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    if !enabled {
+        state.auto_reopen.lock().remove(&port_name);
+        return Ok(());
+    }
+
+    let config = state
+        .configs
+        .lock()
+        .get(&port_name)
+        .cloned()
+        .ok_or_else(|| format!("Port {} chưa được mở", port_name))?;
+
+    // Lấy VID/PID/serial của thiết bị hiện tại
+    let options = PortListOptions { include_all: true, ..Default::default() };
+    let info = collect_serial_ports(&options)?
+        .into_iter()
+        .find(|p| p.name == port_name);
+
+    state.auto_reopen.lock().insert(port_name, AutoReopenEntry {
+        config,
+        vid: info.as_ref().and_then(|p| p.vid),
+        pid: info.as_ref().and_then(|p| p.pid),
+        serial_number: info.and_then(|p| p.serial_number),
+    });
+
+    Ok(())
 }
 
 // Mở kết nối serial port
@@ -337,6 +688,15 @@ fn open_port(
     app: AppHandle,
     state: State<SerialState>,
     config: SerialConfig,
+) -> Result<String, String> {
+    open_serial_port(&app, &state, config)
+}
+
+// Mở port và khởi động reader/signal threads (dùng chung cho open_port và auto-reopen)
+fn open_serial_port(
+    app: &AppHandle,
+    state: &SerialState,
+    config: SerialConfig,
 ) -> Result<String, String> {
     let port_name = config.port_name.clone();
 
@@ -348,37 +708,18 @@ fn open_port(
         }
     }
 
-    // Parse data bits
-    let data_bits = match config.data_bits {
-        5 => DataBits::Five,
-        6 => DataBits::Six,
-        7 => DataBits::Seven,
-        8 => DataBits::Eight,
-        _ => return Err("Data bits không hợp lệ".to_string()),
-    };
-
-    // Parse stop bits
-    let stop_bits = match config.stop_bits.as_str() {
-        "1" => StopBits::One,
-        "1.5" => StopBits::Two, // serialport crate không hỗ trợ 1.5, dùng 2
-        "2" => StopBits::Two,
-        _ => return Err("Stop bits không hợp lệ".to_string()),
-    };
-
-    // Parse parity
-    let parity = match config.parity.to_lowercase().as_str() {
-        "none" => Parity::None,
-        "odd" => Parity::Odd,
-        "even" => Parity::Even,
-        _ => return Err("Parity không hợp lệ".to_string()),
-    };
+    // Parse data bits, stop bits, parity, flow control
+    let data_bits = parse_data_bits(config.data_bits)?;
+    let stop_bits = parse_stop_bits(&config.stop_bits)?;
+    let parity = parse_parity(&config.parity)?;
+    let flow_control = parse_flow_control(&config.flow_control)?;
 
     // Mở serial port với timeout ngắn để poll nhanh
     let mut port = serialport::new(&port_name, config.baud_rate)
         .data_bits(data_bits)
         .stop_bits(stop_bits)
         .parity(parity)
-        .flow_control(FlowControl::None)
+        .flow_control(flow_control)
         .timeout(Duration::from_millis(5)) // Timeout ngắn để responsive
         .open()
         .map_err(|e| {
@@ -410,6 +751,12 @@ fn open_port(
         ports.insert(port_name.clone(), port.clone());
     }
 
+    // Lưu cấu hình (reader thread đọc baud rate từ đây)
+    {
+        let mut configs = state.configs.lock();
+        configs.insert(port_name.clone(), config.clone());
+    }
+
     // Đánh dấu port đang chạy
     {
         let mut running = state.running.lock();
@@ -436,12 +783,13 @@ fn open_port(
         };
 
         // Tính gap timeout động dựa vào baud rate (chỉ dùng khi không có line ending)
-        // Công thức: thời gian truyền 256 bytes, min 5ms, max 50ms
-        // Cho phép message interval ~50ms+ được tách riêng
-        let gap_timeout_ms: u64 = {
-            let time_for_256b = (256 * 10 * 1000) / baud as u64; // ms để truyền 256 bytes
-            time_for_256b.clamp(5, 50)
-        };
+        // Tính lại khi baud rate thay đổi qua reconfigure_port
+        let mut current_baud = baud;
+        let mut gap_timeout = gap_timeout_ms(current_baud);
+
+        // Recorder và script engine nhận trực tiếp từng chunk
+        let recorder = app_clone.state::<RecorderState>();
+        let scripts = app_clone.state::<ScriptState>();
 
         // Batching: tích lũy data và emit khi có "gap" hoặc line ending
         let mut accumulated_data: Vec<u8> = Vec::with_capacity(8192);
@@ -458,6 +806,14 @@ fn open_port(
                 }
             }
 
+            // Cập nhật gap timeout nếu baud rate đã thay đổi
+            if let Some(config) = state.configs.lock().get(&port_name_clone) {
+                if config.baud_rate != current_baud {
+                    current_baud = config.baud_rate;
+                    gap_timeout = gap_timeout_ms(current_baud);
+                }
+            }
+
             // Đọc dữ liệu
             let bytes_read = {
                 let ports = state.ports.lock();
@@ -484,6 +840,8 @@ fn open_port(
                     accumulated_data.extend_from_slice(&buffer[..n]);
                     last_data_received = Instant::now();
                     has_pending_data = true;
+                    recorder.record(&port_name_clone, "rx", &buffer[..n], None, None);
+                    scripts.feed(&port_name_clone, &buffer[..n]);
                 }
             }
 
@@ -517,7 +875,7 @@ fn open_port(
             } else {
                 // Gap timeout mode: emit khi có gap trong data stream
                 let should_emit = has_pending_data
-                    && last_data_received.elapsed() > Duration::from_millis(gap_timeout_ms);
+                    && last_data_received.elapsed() > Duration::from_millis(gap_timeout);
 
                 if should_emit {
                     let data = SerialData {
@@ -562,6 +920,10 @@ fn open_port(
                 let mut running = state.running.lock();
                 running.remove(&port_name_clone);
             }
+            {
+                let mut configs = state.configs.lock();
+                configs.remove(&port_name_clone);
+            }
 
             // Emit event về frontend
             let event = PortDisconnected {
@@ -576,6 +938,59 @@ fn open_port(
         }
     });
 
+    // Tạo thread theo dõi tín hiệu modem (CTS, DSR, RI, CD)
+    let port_name_signals = port_name.clone();
+    let app_signals = app.clone();
+
+    thread::spawn(move || {
+        let mut last_signals: Option<(bool, bool, bool, bool)> = None;
+
+        loop {
+            thread::sleep(Duration::from_millis(SERIAL_SIGNAL_POLL_MS));
+
+            let state = unsafe { &*(state_ptr as *const SerialState) };
+            {
+                let running = state.running.lock();
+                if !running.get(&port_name_signals).unwrap_or(&false) {
+                    break;
+                }
+            }
+
+            // Đọc tín hiệu, bỏ qua lỗi tạm thời (reader thread sẽ xử lý disconnect)
+            let signals = {
+                let ports = state.ports.lock();
+                let Some(port) = ports.get(&port_name_signals) else {
+                    break;
+                };
+                let mut port = port.lock();
+                match (
+                    port.read_clear_to_send(),
+                    port.read_data_set_ready(),
+                    port.read_ring_indicator(),
+                    port.read_carrier_detect(),
+                ) {
+                    (Ok(cts), Ok(dsr), Ok(ri), Ok(cd)) => Some((cts, dsr, ri, cd)),
+                    _ => None,
+                }
+            };
+
+            if let Some(signals) = signals {
+                if last_signals != Some(signals) {
+                    last_signals = Some(signals);
+                    let (cts, dsr, ri, cd) = signals;
+                    let _ = app_signals.emit("serial-signals", SerialSignals {
+                        port_name: port_name_signals.clone(),
+                        cts,
+                        dsr,
+                        ri,
+                        cd,
+                        timestamp: get_timestamp(),
+                    });
+                }
+            }
+        }
+    });
+
     Ok(format!("Đã mở port {} thành công", port_name))
 }
 
@@ -602,13 +1017,156 @@ fn close_port(state: State<SerialState>, port_name: String) -> Result<String, St
         running.remove(&port_name);
     }
 
+    {
+        let mut configs = state.configs.lock();
+        configs.remove(&port_name);
+    }
+
+    // Đóng chủ động thì không tự mở lại
+    {
+        let mut auto_reopen = state.auto_reopen.lock();
+        auto_reopen.remove(&port_name);
+    }
+
     Ok(format!("Đã đóng port {}", port_name))
 }
 
+// Tự động dò baud rate: thử lần lượt các baud rate và chấm điểm dữ liệu nhận được
+// Port được đóng sau mỗi lần thử, không giữ port mở sau khi dò xong
+#[tauri::command]
+async fn autodetect_baud(
+    state: State<'_, SerialState>,
+    config: BaudDetectConfig,
+) -> Result<Vec<BaudCandidate>, String> {
+    {
+        let ports = state.ports.lock();
+        if ports.contains_key(&config.port_name) {
+            return Err(format!("Port {} đã được mở", config.port_name));
+        }
+    }
+
+    let data_bits = parse_data_bits(config.data_bits)?;
+    let stop_bits = parse_stop_bits(&config.stop_bits)?;
+    let parity = parse_parity(&config.parity)?;
+    let baud_rates = config.candidate_baud_rates()?;
+
+    // Chạy trên thread riêng vì việc đọc serial là blocking
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+
+    thread::spawn(move || {
+        let mut candidates = Vec::with_capacity(baud_rates.len());
+
+        for baud in baud_rates {
+            let mut port = match serialport::new(&config.port_name, baud)
+                .data_bits(data_bits)
+                .stop_bits(stop_bits)
+                .parity(parity)
+                .flow_control(FlowControl::None)
+                .timeout(Duration::from_millis(5))
+                .open()
+            {
+                Ok(p) => p,
+                Err(e) => {
+                    let _ = result_tx.send(Err(format!("Không thể mở port {}: {}", config.port_name, e)));
+                    return;
+                }
+            };
+
+            let _ = port.clear(serialport::ClearBuffer::All);
+            if let Some(ref probe) = config.probe {
+                let _ = port.write_all(probe);
+                let _ = port.flush();
+            }
+
+            // Tách dữ liệu thành các burst theo khoảng lặng (3.5 ký tự)
+            let gap = Duration::from_micros(calculate_inter_frame_delay_us(baud));
+            let mut chunks: Vec<Vec<u8>> = Vec::new();
+            let mut current: Vec<u8> = Vec::new();
+            let mut last_byte_time = Instant::now();
+            let mut buffer = [0u8; 1024];
+            let deadline = Instant::now() + Duration::from_millis(config.sample_ms);
+
+            while Instant::now() < deadline {
+                match port.read(&mut buffer) {
+                    Ok(n) if n > 0 => {
+                        if !current.is_empty() && last_byte_time.elapsed() > gap {
+                            chunks.push(std::mem::take(&mut current));
+                        }
+                        current.extend_from_slice(&buffer[..n]);
+                        last_byte_time = Instant::now();
+                    }
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                    Err(_) => break,
+                }
+            }
+            if !current.is_empty() {
+                chunks.push(current);
+            }
+
+            // Đóng port trước khi thử baud rate tiếp theo
+            drop(port);
+
+            candidates.push(score_baud_sample(baud, &chunks));
+        }
+
+        rank_candidates(&mut candidates);
+        let _ = result_tx.send(Ok(candidates));
+    });
+
+    result_rx
+        .await
+        .map_err(|_| "Dò baud rate bị gián đoạn".to_string())?
+}
+
+// Đổi baud rate và framing của port đang mở (không đóng port, giữ reader thread)
+#[tauri::command]
+fn reconfigure_port(
+    state: State<SerialState>,
+    config: SerialReconfigureConfig,
+) -> Result<String, String> {
+    let port_name = config.port_name.clone();
+
+    let data_bits = parse_data_bits(config.data_bits)?;
+    let stop_bits = parse_stop_bits(&config.stop_bits)?;
+    let parity = parse_parity(&config.parity)?;
+
+    {
+        let ports = state.ports.lock();
+        let port = ports
+            .get(&port_name)
+            .ok_or_else(|| format!("Port {} chưa được mở", port_name))?;
+
+        // Lỗi giữa chừng: port được trả về cấu hình cũ, config đã lưu giữ nguyên
+        let mut port = port.lock();
+        reconfigure_line(port.as_mut(), LineSettings {
+            baud_rate: config.baud_rate,
+            data_bits,
+            stop_bits,
+            parity,
+        })
+        .map_err(|e| format!("Không thể cấu hình lại port {}: {}", port_name, e))?;
+    }
+
+    // Cập nhật cấu hình để reader thread tính lại gap timeout
+    {
+        let mut configs = state.configs.lock();
+        if let Some(saved) = configs.get_mut(&port_name) {
+            saved.baud_rate = config.baud_rate;
+            saved.data_bits = config.data_bits;
+            saved.stop_bits = config.stop_bits.clone();
+            saved.parity = config.parity.clone();
+        }
+    }
+
+    Ok(format!("Đã cấu hình lại port {} ({} baud)", port_name, config.baud_rate))
+}
+
 // Gửi dữ liệu qua serial port
 #[tauri::command]
 fn send_data(
     state: State<SerialState>,
+    recorder: State<RecorderState>,
     port_name: String,
     data: String,
     is_hex: bool,
@@ -668,9 +1226,65 @@ fn send_data(
         }
     }
 
+    recorder.record(&port_name, "tx", &bytes, None, None);
+
     Ok(format!("Đã gửi {} bytes", total_bytes))
 }
 
+// Set DTR cho port đang mở
+#[tauri::command]
+fn set_dtr(state: State<SerialState>, port_name: String, level: bool) -> Result<(), String> {
+    let ports = state.ports.lock();
+    let port = ports
+        .get(&port_name)
+        .ok_or_else(|| format!("Port {} chưa được mở", port_name))?;
+
+    let mut port = port.lock();
+    port.write_data_terminal_ready(level)
+        .map_err(|e| format!("Không thể set DTR: {}", e))
+}
+
+// Set RTS cho port đang mở
+#[tauri::command]
+fn set_rts(state: State<SerialState>, port_name: String, level: bool) -> Result<(), String> {
+    let ports = state.ports.lock();
+    let port = ports
+        .get(&port_name)
+        .ok_or_else(|| format!("Port {} chưa được mở", port_name))?;
+
+    let mut port = port.lock();
+    port.write_request_to_send(level)
+        .map_err(|e| format!("Không thể set RTS: {}", e))
+}
+
+// Gửi break condition trong khoảng thời gian duration_ms (mặc định 250ms, tối đa 5000ms)
+// Chờ bất đồng bộ và không giữ lock port trong lúc break, UI và reader thread không bị treo
+#[tauri::command]
+async fn send_break(
+    state: State<'_, SerialState>,
+    port_name: String,
+    duration_ms: Option<u64>,
+) -> Result<(), String> {
+    let duration_ms = duration_ms.unwrap_or(SERIAL_BREAK_DEFAULT_MS);
+    if duration_ms == 0 || duration_ms > SERIAL_BREAK_MAX_MS {
+        return Err(format!("Thời gian break phải trong khoảng 1-{} ms", SERIAL_BREAK_MAX_MS));
+    }
+
+    let port = state
+        .ports
+        .lock()
+        .get(&port_name)
+        .cloned()
+        .ok_or_else(|| format!("Port {} chưa được mở", port_name))?;
+
+    port.lock()
+        .set_break()
+        .map_err(|e| format!("Không thể gửi break: {}", e))?;
+    tokio::time::sleep(Duration::from_millis(duration_ms)).await;
+    let result = port.lock().clear_break();
+    result.map_err(|e| format!("Không thể clear break: {}", e))
+}
+
 // Kiểm tra trạng thái kết nối
 #[tauri::command]
 fn is_port_open(state: State<SerialState>, port_name: String) -> bool {
@@ -806,6 +1420,8 @@ fn tcp_client_connect(
                                 }
                                 Ok(n) => {
                                     accumulated_data.extend_from_slice(&buffer[..n]);
+                                    app_read.state::<RecorderState>().record(&conn_id_read, "rx", &buffer[..n], None, None);
+                                    app_read.state::<ScriptState>().feed(&conn_id_read, &buffer[..n]);
 
                                     if !accumulated_data.is_empty() {
                                         let _ = app_read.emit("tcp-data", TcpData {
@@ -1000,6 +1616,7 @@ fn tcp_client_disconnect(state: State<TcpState>, connection_id: String) -> Resul
 #[tauri::command]
 fn tcp_client_send(
     state: State<TcpState>,
+    recorder: State<RecorderState>,
     connection_id: String,
     data: String,
     is_hex: bool,
@@ -1030,9 +1647,11 @@ fn tcp_client_send(
 
     let total_bytes = bytes.len();
 
-    handle.tx.try_send(bytes)
+    handle.tx.try_send(bytes.clone())
         .map_err(|_| "Không thể gửi data".to_string())?;
 
+    recorder.record(&connection_id, "tx", &bytes, None, None);
+
     Ok(format!("Đã gửi {} bytes", total_bytes))
 }
 
@@ -1203,6 +1822,8 @@ fn tcp_server_start(
                                                 Ok(0) => break, // Client disconnected
                                                 Ok(n) => {
                                                     let received_data = buffer[..n].to_vec();
+                                                    let recorder = app_ref.state::<RecorderState>();
+                                                    recorder.record(&server_id_ref, "rx", &received_data, Some(&client_id_ref), None);
 
                                                     // Emit data to frontend
                                                     let _ = app_ref.emit("tcp-data", TcpData {
@@ -1223,6 +1844,7 @@ fn tcp_server_start(
                                                             break;
                                                         }
                                                         let _ = write_half.flush().await;
+                                                        app_ref.state::<RecorderState>().record(&server_id_ref, "tx", &echo_bytes, Some(&client_id_ref), None);
                                                     }
                                                 }
                                                 Err(_) => break,
@@ -1322,6 +1944,7 @@ fn tcp_server_set_echo(state: State<TcpState>, server_id: String, enabled: bool)
 #[tauri::command]
 fn tcp_server_send(
     state: State<TcpState>,
+    recorder: State<RecorderState>,
     server_id: String,
     client_id: Option<String>,
     data: String,
@@ -1357,8 +1980,9 @@ fn tcp_server_send(
     if let Some(target_id) = client_id {
         // Gửi đến 1 client cụ thể
         if let Some(client) = clients.get(&target_id) {
-            client.tx.try_send(bytes)
+            client.tx.try_send(bytes.clone())
                 .map_err(|_| "Không thể gửi data".to_string())?;
+            recorder.record(&server_id, "tx", &bytes, Some(&target_id), None);
         } else {
             return Err(format!("Client {} không tồn tại", target_id));
         }
@@ -1367,6 +1991,7 @@ fn tcp_server_send(
         for client in clients.values() {
             let _ = client.tx.try_send(bytes.clone());
         }
+        recorder.record(&server_id, "tx", &bytes, None, None);
     }
 
     Ok(format!("Đã gửi {} bytes", total_bytes))
@@ -1430,6 +2055,10 @@ fn modbus_rtu_connect(
 ) -> Result<String, String> {
     let connection_id = format!("modbus-rtu-{}", config.port_name.replace("/", "_"));
 
+    if config.mode == ModbusMode::Tcp {
+        return Err("Serial chỉ hỗ trợ mode rtu hoặc ascii".to_string());
+    }
+
     // Kiểm tra xem connection đã tồn tại chưa
     {
         let connections = state.connections.lock();
@@ -1439,34 +2068,18 @@ fn modbus_rtu_connect(
     }
 
     // Parse serial config
-    let data_bits = match config.data_bits {
-        5 => DataBits::Five,
-        6 => DataBits::Six,
-        7 => DataBits::Seven,
-        8 => DataBits::Eight,
-        _ => return Err("Data bits không hợp lệ".to_string()),
-    };
+    let data_bits = parse_data_bits(config.data_bits)?;
+    let stop_bits = parse_stop_bits(&config.stop_bits)?;
+    let parity = parse_parity(&config.parity)?;
 
-    let stop_bits = match config.stop_bits.as_str() {
-        "1" => StopBits::One,
-        "1.5" => StopBits::Two,
-        "2" => StopBits::Two,
-        _ => return Err("Stop bits không hợp lệ".to_string()),
-    };
-
-    let parity = match config.parity.to_lowercase().as_str() {
-        "none" => Parity::None,
-        "odd" => Parity::Odd,
-        "even" => Parity::Even,
-        _ => return Err("Parity không hợp lệ".to_string()),
-    };
+    let flow_control = parse_flow_control(&config.flow_control)?;
 
     // Mở serial port
     let port = serialport::new(&config.port_name, config.baud_rate)
         .data_bits(data_bits)
         .stop_bits(stop_bits)
         .parity(parity)
-        .flow_control(FlowControl::None)
+        .flow_control(flow_control)
         .timeout(Duration::from_millis(config.response_timeout_ms as u64))
         .open()
         .map_err(|e| format!("Không thể mở port {}: {}", config.port_name, e))?;
@@ -1475,7 +2088,7 @@ fn modbus_rtu_connect(
 
     // Tạo connection handle
     let handle = ModbusConnectionHandle {
-        mode: ModbusMode::Rtu,
+        mode: config.mode,
         config: ModbusConnectionConfig::Rtu {
             port_name: config.port_name.clone(),
             baud_rate: config.baud_rate,
@@ -1487,7 +2100,7 @@ fn modbus_rtu_connect(
         running: AtomicBool::new(true),
         serial_port: Some(port),
         tcp_tx: None,
-        tcp_rx: None,
+        tcp_pending: None,
     };
 
     // Lưu connection
@@ -1514,7 +2127,11 @@ fn modbus_tcp_connect(
     state: State<ModbusState>,
     config: ModbusTcpConfig,
 ) -> Result<String, String> {
-    let connection_id = format!("modbus-tcp-{}:{}", config.host, config.port);
+    let connection_id = if config.rtu_over_tcp {
+        format!("modbus-rtu-tcp-{}:{}", config.host, config.port)
+    } else {
+        format!("modbus-tcp-{}:{}", config.host, config.port)
+    };
 
     // Kiểm tra xem connection đã tồn tại chưa
     {
@@ -1529,25 +2146,37 @@ fn modbus_tcp_connect(
     let connection_id_clone = connection_id.clone();
     let connections_clone = state.connections.clone();
 
-    // Channels cho việc gửi/nhận data
+    // Channel gửi request; response được chuyển theo transaction ID
     let (tx, rx) = mpsc::channel::<Vec<u8>>(100);
-    let (response_tx, response_rx) = mpsc::channel::<Vec<u8>>(100);
+    let pending: PendingTransactions = Arc::new(Mutex::new(HashMap::new()));
 
-    // Tạo connection handle trước
-    let handle = ModbusConnectionHandle {
-        mode: ModbusMode::Tcp,
-        config: ModbusConnectionConfig::Tcp {
+    let rtu_over_tcp = config.rtu_over_tcp;
+    let (mode, connection_config) = if rtu_over_tcp {
+        (ModbusMode::Rtu, ModbusConnectionConfig::RtuOverTcp {
+            host: config.host.clone(),
+            port: config.port,
+            slave_id: config.unit_id,
+            response_timeout_ms: config.response_timeout_ms,
+        })
+    } else {
+        (ModbusMode::Tcp, ModbusConnectionConfig::Tcp {
             host: config.host.clone(),
             port: config.port,
             unit_id: config.unit_id,
             response_timeout_ms: config.response_timeout_ms,
-        },
+        })
+    };
+
+    // Tạo connection handle trước
+    let handle = ModbusConnectionHandle {
+        mode,
+        config: connection_config,
         transaction_id: AtomicU16::new(0),
         polling_active: AtomicBool::new(false),
         running: AtomicBool::new(true),
         serial_port: None,
         tcp_tx: Some(tx),
-        tcp_rx: Some(Arc::new(Mutex::new(response_rx))),
+        tcp_pending: Some(pending.clone()),
     };
 
     // Lưu connection
@@ -1570,24 +2199,74 @@ fn modbus_tcp_connect(
                 let (mut read_half, mut write_half) = stream.into_split();
                 let mut rx = rx;
 
-                // Task đọc responses
-                let response_tx_clone = response_tx.clone();
+                // RTU over TCP: request đang chờ và buffer đọc, được reset mỗi khi gửi request mới
+                let stream_buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(Vec::new()));
+                let last_request: Arc<Mutex<Option<Vec<u8>>>> = Arc::new(Mutex::new(None));
+                let write_buffer = stream_buffer.clone();
+                let write_request = last_request.clone();
+
+                // Task đọc responses: ghép frame theo MBAP length, chuyển theo transaction ID
                 let read_task = tokio::spawn(async move {
                     let mut buffer = [0u8; 1024];
                     loop {
                         match read_half.read(&mut buffer).await {
                             Ok(0) => break,
                             Ok(n) => {
-                                let _ = response_tx_clone.send(buffer[..n].to_vec()).await;
+                                let mut stream_buffer = stream_buffer.lock();
+                                stream_buffer.extend_from_slice(&buffer[..n]);
+                                loop {
+                                    // RTU over TCP không có transaction ID: chỉ 1 request chờ, key 0.
+                                    // Response muộn của request trước (khác FC / độ dài) bị bỏ qua
+                                    let (frame, transaction_id) = if rtu_over_tcp {
+                                        let Some(frame) = take_rtu_frame(&mut stream_buffer) else {
+                                            break;
+                                        };
+                                        let matches = last_request
+                                            .lock()
+                                            .as_ref()
+                                            .is_some_and(|request| is_rtu_reply_to(request, &frame));
+                                        if !matches {
+                                            eprintln!("Modbus RTU over TCP: discarded frame not matching the request");
+                                            continue;
+                                        }
+                                        (frame, 0)
+                                    } else {
+                                        match take_mbap_frame(&mut stream_buffer) {
+                                            Some(frame) => {
+                                                let transaction_id = mbap_transaction_id(&frame).unwrap_or(0);
+                                                (frame, transaction_id)
+                                            }
+                                            None => break,
+                                        }
+                                    };
+                                    let waiter = pending.lock().remove(&transaction_id);
+                                    match waiter {
+                                        Some(waiter) => {
+                                            let _ = waiter.send(frame);
+                                        }
+                                        // Response đến muộn (đã timeout) hoặc không có request tương ứng
+                                        None => eprintln!(
+                                            "Modbus TCP: discarded stale response (transaction {})",
+                                            transaction_id
+                                        ),
+                                    }
+                                }
                             }
                             Err(_) => break,
                         }
                     }
+                    // Mất kết nối: huỷ các request đang chờ
+                    pending.lock().clear();
                 });
 
                 // Task gửi requests
                 let write_task = tokio::spawn(async move {
                     while let Some(data) = rx.recv().await {
+                        if rtu_over_tcp {
+                            // Bỏ byte còn sót của response cũ trước khi gửi request mới
+                            write_buffer.lock().clear();
+                            *write_request.lock() = Some(data.clone());
+                        }
                         if write_half.write_all(&data).await.is_err() {
                             break;
                         }
@@ -1669,22 +2348,397 @@ fn modbus_request(
         .get(&request.connection_id)
         .ok_or_else(|| format!("Connection {} không tồn tại", request.connection_id))?;
 
+    let response = execute_modbus_request(handle, &request, None)?;
+
+    // Emit response event
+    let _ = app.emit("modbus-response", response.clone());
+
+    Ok(response)
+}
+
+/// Scan dải slave ID trên 1 connection (tuỳ chọn quét baud rate / parity với RTU)
+#[tauri::command]
+async fn modbus_scan(
+    app: AppHandle,
+    state: State<'_, ModbusState>,
+    config: ModbusScanConfig,
+) -> Result<Vec<ModbusScanResult>, String> {
+    if config.start_id == 0 || config.start_id > config.end_id {
+        return Err("Dải slave ID không hợp lệ".to_string());
+    }
+
+    let port = {
+        let connections = state.connections.lock();
+        let handle = connections
+            .get(&config.connection_id)
+            .ok_or_else(|| format!("Connection {} không tồn tại", config.connection_id))?;
+        handle.serial_port.clone()
+    };
+
+    // Các tổ hợp (baud, parity) cần quét; None = giữ cấu hình hiện tại
+    let sweep = !config.baud_rates.is_empty() || !config.parities.is_empty();
+    if sweep && port.is_none() {
+        return Err("Quét baud/parity chỉ hỗ trợ kết nối serial".to_string());
+    }
+    for &baud in &config.baud_rates {
+        if baud == 0 {
+            return Err("Baud rate không hợp lệ: 0".to_string());
+        }
+    }
+    let baud_rates: Vec<Option<u32>> = if sweep && !config.baud_rates.is_empty() {
+        config.baud_rates.iter().copied().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    let parities: Vec<Option<String>> = if sweep && !config.parities.is_empty() {
+        config.parities.iter().cloned().map(Some).collect()
+    } else {
+        vec![None]
+    };
+    for parity in parities.iter().flatten() {
+        parse_parity(parity)?;
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let mut scans = state.scans.lock();
+        if scans.contains_key(&config.connection_id) {
+            return Err(format!("Connection {} đang scan", config.connection_id));
+        }
+        scans.insert(config.connection_id.clone(), running.clone());
+    }
+
+    let connections = state.connections.clone();
+    let scans = state.scans.clone();
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+
+    // Chạy trên thread riêng vì request Modbus là blocking
+    thread::spawn(move || {
+        let ids: Vec<u8> = (config.start_id..=config.end_id).collect();
+        let total = ids.len() * baud_rates.len() * parities.len();
+        let mut scanned = 0;
+        let mut found = Vec::new();
+
+        // Lưu cấu hình port để khôi phục sau khi quét
+        let original = port.as_ref().and_then(|p| {
+            let p = p.lock();
+            Some((p.baud_rate().ok()?, p.parity().ok()?))
+        });
+
+        'scan: for baud_rate in &baud_rates {
+            for parity in &parities {
+                if let Some(port) = &port {
+                    let mut port = port.lock();
+                    if let Some(baud) = baud_rate {
+                        let _ = port.set_baud_rate(*baud);
+                    }
+                    if let Some(parity) = parity {
+                        let _ = port.set_parity(parse_parity(parity).unwrap_or(Parity::None));
+                    }
+                }
+                // Inter-frame delay tính theo baud trong config của handle
+                if let Some(baud) = baud_rate {
+                    set_connection_baud_rate(&connections, &config.connection_id, *baud);
+                }
+
+                for &slave_id in &ids {
+                    if !running.load(Ordering::SeqCst) {
+                        break 'scan;
+                    }
+
+                    let request = ModbusRequest {
+                        connection_id: config.connection_id.clone(),
+                        slave_id: Some(slave_id),
+                        function_code: config.function_code,
+                        start_address: config.start_address,
+                        quantity: config.quantity,
+                        read_device_id_code: (config.function_code == 0x2B).then_some(0x01),
+                        ..Default::default()
+                    };
+
+                    let result = {
+                        let connections = connections.lock();
+                        let Some(handle) = connections.get(&config.connection_id) else {
+                            break 'scan;
+                        };
+                        execute_modbus_request(handle, &request, Some(config.timeout_ms))
+                    };
+
+                    // Response thường hoặc exception đều là slave có tồn tại,
+                    // trừ exception 0x0A/0x0B của gateway (không có thiết bị phía sau)
+                    let hit = result
+                        .ok()
+                        .filter(|response| is_device_reply(response.error_code))
+                        .map(|response| ModbusScanResult {
+                            slave_id,
+                            baud_rate: *baud_rate,
+                            parity: parity.clone(),
+                            exception_code: response.error_code,
+                            response_time_ms: response.response_time_ms,
+                            device_identification: response.device_identification,
+                        });
+
+                    scanned += 1;
+                    let _ = app.emit("modbus-scan-progress", ModbusScanProgress {
+                        connection_id: config.connection_id.clone(),
+                        slave_id,
+                        baud_rate: *baud_rate,
+                        parity: parity.clone(),
+                        scanned,
+                        total,
+                        found: hit.clone(),
+                    });
+
+                    if let Some(hit) = hit {
+                        found.push(hit);
+                    }
+                }
+            }
+        }
+
+        if let (Some(port), Some((baud, parity))) = (&port, original) {
+            let mut port = port.lock();
+            let _ = port.set_baud_rate(baud);
+            let _ = port.set_parity(parity);
+            drop(port);
+            set_connection_baud_rate(&connections, &config.connection_id, baud);
+        }
+
+        scans.lock().remove(&config.connection_id);
+        let _ = result_tx.send(found);
+    });
+
+    result_rx
+        .await
+        .map_err(|_| "Scan bị gián đoạn".to_string())
+}
+
+/// Cập nhật baud rate trong config của connection RTU (sau khi đổi baud trên port)
+fn set_connection_baud_rate(
+    connections: &Mutex<HashMap<String, ModbusConnectionHandle>>,
+    connection_id: &str,
+    baud: u32,
+) {
+    if let Some(ModbusConnectionHandle {
+        config: ModbusConnectionConfig::Rtu { baud_rate, .. },
+        ..
+    }) = connections.lock().get_mut(connection_id)
+    {
+        *baud_rate = baud;
+    }
+}
+
+/// Quét bản đồ thanh ghi của 1 slave: tìm các dải địa chỉ đọc được trong từng bảng
+#[tauri::command]
+async fn modbus_map_scan(
+    app: AppHandle,
+    state: State<'_, ModbusState>,
+    config: ModbusMapScanConfig,
+) -> Result<Vec<RegisterRange>, String> {
+    if config.start_address > config.end_address {
+        return Err("Dải địa chỉ không hợp lệ".to_string());
+    }
+
+    let tables: Vec<String> = if config.tables.is_empty() {
+        ["coil", "discrete_input", "holding_register", "input_register"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect()
+    } else {
+        config.tables.clone()
+    };
+    for table in &tables {
+        if table_read_function_code(table).is_none() {
+            return Err(format!("Bảng không hợp lệ: {}", table));
+        }
+    }
+
+    if !state.connections.lock().contains_key(&config.connection_id) {
+        return Err(format!("Connection {} không tồn tại", config.connection_id));
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let mut scans = state.scans.lock();
+        if scans.contains_key(&config.connection_id) {
+            return Err(format!("Connection {} đang scan", config.connection_id));
+        }
+        scans.insert(config.connection_id.clone(), running.clone());
+    }
+
+    let connections = state.connections.clone();
+    let scans = state.scans.clone();
+    let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+
+    // Chạy trên thread riêng vì request Modbus là blocking
+    thread::spawn(move || {
+        let mut found: Vec<RegisterRange> = Vec::new();
+        let mut probes = 0u32;
+
+        for table in &tables {
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+            let function_code = table_read_function_code(table).unwrap_or(0x03);
+
+            let ranges = scan_address_space(
+                config.start_address,
+                config.end_address,
+                config.max_block,
+                config.max_gap_step,
+                |address, quantity, table_ranges| {
+                    if !running.load(Ordering::SeqCst) {
+                        return ProbeOutcome::Stopped;
+                    }
+
+                    let request = ModbusRequest {
+                        connection_id: config.connection_id.clone(),
+                        slave_id: config.slave_id,
+                        function_code,
+                        start_address: address,
+                        quantity,
+                        ..Default::default()
+                    };
+
+                    let result = {
+                        let connections = connections.lock();
+                        let Some(handle) = connections.get(&config.connection_id) else {
+                            return ProbeOutcome::Stopped;
+                        };
+                        execute_modbus_request(handle, &request, config.timeout_ms)
+                    };
+
+                    probes += 1;
+                    let _ = app.emit("modbus-map-scan-progress", ModbusMapScanProgress {
+                        connection_id: config.connection_id.clone(),
+                        table: table.clone(),
+                        address,
+                        probes,
+                        ranges_found: found.len() + table_ranges,
+                    });
+
+                    // Exception 0x01: slave không hỗ trợ bảng này, bỏ qua cả bảng
+                    match result {
+                        Ok(response) => match response.error_code {
+                            None if response.success => ProbeOutcome::Ok,
+                            Some(0x01) => ProbeOutcome::IllegalFunction,
+                            _ => ProbeOutcome::Failed,
+                        },
+                        Err(_) => ProbeOutcome::Failed,
+                    }
+                },
+            );
+
+            found.extend(ranges.into_iter().map(|(start, end)| RegisterRange {
+                table: table.clone(),
+                start_address: start,
+                end_address: end,
+                count: (end - start) as u32 + 1,
+            }));
+        }
+
+        scans.lock().remove(&config.connection_id);
+        let _ = result_tx.send(found);
+    });
+
+    result_rx
+        .await
+        .map_err(|_| "Scan bị gián đoạn".to_string())
+}
+
+/// Dừng scan đang chạy (trả về kết quả đã tìm được)
+#[tauri::command]
+fn modbus_scan_stop(state: State<ModbusState>, connection_id: String) -> Result<(), String> {
+    let scans = state.scans.lock();
+    let running = scans
+        .get(&connection_id)
+        .ok_or_else(|| format!("Connection {} không scan", connection_id))?;
+    running.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+// Gửi 1 request và chờ response (không emit event).
+// timeout_override dùng cho scan (timeout ngắn hơn cấu hình connection)
+fn execute_modbus_request(
+    handle: &ModbusConnectionHandle,
+    request: &ModbusRequest,
+    timeout_override: Option<u32>,
+) -> Result<ModbusResponse, String> {
     let start_time = Instant::now();
 
     // Build request data
-    let request_data = build_request_data(&request)?;
+    let request_data = build_request_data(request)?;
 
     // Get slave/unit ID and timeout from config
     let (slave_id, timeout_ms) = match &handle.config {
-        ModbusConnectionConfig::Rtu { slave_id, response_timeout_ms, .. } => {
+        ModbusConnectionConfig::Rtu { slave_id, response_timeout_ms, .. }
+        | ModbusConnectionConfig::RtuOverTcp { slave_id, response_timeout_ms, .. } => {
             (*slave_id, *response_timeout_ms)
         }
         ModbusConnectionConfig::Tcp { unit_id, response_timeout_ms, .. } => {
             (*unit_id, *response_timeout_ms)
         }
     };
+    let slave_id = request.slave_id.unwrap_or(slave_id);
+    let timeout_ms = timeout_override.unwrap_or(timeout_ms);
+    let mut transaction_id = handle.transaction_id.load(Ordering::Relaxed);
 
     let (request_frame, response_frame, parsed) = match handle.mode {
+        ModbusMode::Rtu if matches!(handle.config, ModbusConnectionConfig::RtuOverTcp { .. }) => {
+            // RTU over TCP: gửi frame RTU qua socket, response được ghép theo CRC
+            let frame = build_rtu_frame(slave_id, request.function_code, &request_data);
+            let transaction = send_tcp_frame(handle, 0, frame)?;
+
+            let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+            let response_frame = wait_tcp_transaction(&transaction, deadline)?;
+
+            let parsed = parse_rtu_response(&response_frame, request.function_code)?;
+            if parsed.slave_id != slave_id {
+                return Err(format!("Slave ID mismatch: expected {}, got {}", slave_id, parsed.slave_id));
+            }
+
+            (transaction.frame, response_frame, parsed)
+        }
+        ModbusMode::Ascii => {
+            let frame = build_ascii_frame(slave_id, request.function_code, &request_data);
+
+            let port = handle.serial_port.as_ref()
+                .ok_or("Serial port not available")?;
+            let mut port = port.lock();
+
+            let _ = port.clear(serialport::ClearBuffer::All);
+            port.write_all(&frame)
+                .map_err(|e| format!("Send error: {}", e))?;
+            port.flush()
+                .map_err(|e| format!("Flush error: {}", e))?;
+
+            // ASCII: đọc tới khi có đủ frame ':' .. CRLF
+            let mut buffer: Vec<u8> = Vec::new();
+            let mut chunk = [0u8; 256];
+            let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+            // Timeout đọc của port giới hạn theo deadline (timeout khi connect có thể dài hơn)
+            let mut reader = DeadlineReader::new(port.as_mut(), deadline);
+            let response_frame = loop {
+                if let Some(response_frame) = take_ascii_frame(&mut buffer) {
+                    break response_frame;
+                }
+                if Instant::now() >= deadline {
+                    return Err("Response timeout".to_string());
+                }
+                match reader.read(&mut chunk) {
+                    Ok(n) => buffer.extend_from_slice(&chunk[..n]),
+                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                    Err(e) => return Err(format!("Read error: {}", e)),
+                }
+            };
+
+            let parsed = parse_ascii_response(&response_frame, request.function_code)?;
+            if parsed.slave_id != slave_id {
+                return Err(format!("Slave ID mismatch: expected {}, got {}", slave_id, parsed.slave_id));
+            }
+
+            (frame, response_frame, parsed)
+        }
         ModbusMode::Rtu => {
             // Build RTU frame
             let frame = build_rtu_frame(slave_id, request.function_code, &request_data);
@@ -1722,9 +2776,11 @@ fn modbus_request(
             let mut response_buffer = vec![0u8; expected_len + 10]; // Extra buffer
             let mut total_read = 0;
             let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+            // Timeout đọc của port giới hạn theo deadline (timeout khi connect có thể dài hơn)
+            let mut reader = DeadlineReader::new(port.as_mut(), deadline);
 
             while total_read < expected_len && Instant::now() < deadline {
-                match port.read(&mut response_buffer[total_read..]) {
+                match reader.read(&mut response_buffer[total_read..]) {
                     Ok(n) => {
                         total_read += n;
                         if total_read >= 5 { // Minimum RTU response
@@ -1745,72 +2801,55 @@ fn modbus_request(
                 return Err("Response timeout".to_string());
             }
 
+            drop(reader);
             let response_frame = response_buffer[..total_read].to_vec();
 
             // Parse response
             let parsed = parse_rtu_response(&response_frame, request.function_code)?;
 
+            // Nhiều slave trên cùng bus: bỏ qua response của slave khác
+            if parsed.slave_id != slave_id {
+                return Err(format!("Slave ID mismatch: expected {}, got {}", slave_id, parsed.slave_id));
+            }
+
             (frame, response_frame, parsed)
         }
         ModbusMode::Tcp => {
-            // Get transaction ID
-            let transaction_id = handle.transaction_id.fetch_add(1, Ordering::Relaxed);
+            let transaction = send_tcp_transaction(handle, slave_id, request.function_code, &request_data)?;
+            transaction_id = transaction.transaction_id;
 
-            // Build TCP frame
-            let frame = build_tcp_frame(transaction_id, slave_id, request.function_code, &request_data);
-
-            // Get TX channel
-            let tx = handle.tcp_tx.as_ref()
-                .ok_or("TCP channel not available")?;
-
-            // Send request (non-blocking)
-            tx.try_send(frame.clone())
-                .map_err(|_| "Failed to send request")?;
-
-            // Get RX channel and wait for response
-            let rx_arc = handle.tcp_rx.as_ref()
-                .ok_or("TCP response channel not available")?;
-
-            // Try to receive response with timeout
-            let response_frame = {
-                let mut rx = rx_arc.lock();
-                let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
-
-                loop {
-                    if Instant::now() >= deadline {
-                        return Err("Response timeout".to_string());
-                    }
-
-                    // Use blocking_recv with timeout simulation
-                    match rx.try_recv() {
-                        Ok(data) => break data,
-                        Err(mpsc::error::TryRecvError::Empty) => {
-                            thread::sleep(Duration::from_millis(10));
-                            continue;
-                        }
-                        Err(_) => return Err("Channel closed".to_string()),
-                    }
-                }
-            };
+            let deadline = Instant::now() + Duration::from_millis(timeout_ms as u64);
+            let response_frame = wait_tcp_transaction(&transaction, deadline)?;
 
             // Parse response
             let parsed = parse_tcp_response(&response_frame, request.function_code)?;
 
-            (frame, response_frame, parsed)
+            (transaction.frame, response_frame, parsed)
         }
     };
 
     let response_time = start_time.elapsed().as_millis() as u64;
 
+    Ok(build_modbus_response(request, transaction_id, request_frame, response_frame, parsed, response_time))
+}
+
+// Tạo ModbusResponse từ response đã parse (áp dụng decode nếu request có yêu cầu)
+fn build_modbus_response(
+    request: &ModbusRequest,
+    transaction_id: u16,
+    request_frame: Vec<u8>,
+    response_frame: Vec<u8>,
+    parsed: ParsedResponse,
+    response_time_ms: u64,
+) -> ModbusResponse {
     // Truncate coils to requested quantity (backend unpacks all bits from bytes)
     let truncated_coils = parsed.coils.map(|coils| {
         coils.into_iter().take(request.quantity as usize).collect()
     });
 
-    // Build response
-    let response = ModbusResponse {
+    let mut response = ModbusResponse {
         connection_id: request.connection_id.clone(),
-        transaction_id: handle.transaction_id.load(Ordering::Relaxed),
+        transaction_id,
         slave_id: parsed.slave_id,
         function_code: parsed.function_code,
         start_address: request.start_address,
@@ -1822,39 +2861,180 @@ fn modbus_request(
         error_message: parsed.exception_code.map(format_exception_error),
         request_frame,
         response_frame,
-        response_time_ms: response_time,
+        response_time_ms,
         timestamp: modbus::get_timestamp(),
+        device_identification: parsed.device_identification,
+        server_id: parsed.server_id,
+        decoded: None,
     };
+    apply_decode(&mut response, request.decode.as_ref());
+    response
+}
 
-    // Emit response event
-    let _ = app.emit("modbus-response", response.clone());
+// 1 request Modbus TCP đã gửi, đang chờ response theo transaction ID
+struct TcpTransaction {
+    transaction_id: u16,
+    frame: Vec<u8>,
+    sent_at: Instant,
+    response_rx: std::sync::mpsc::Receiver<Vec<u8>>,
+    pending: PendingTransactions,
+}
 
-    Ok(response)
+// Gửi 1 request Modbus TCP (không chờ response)
+fn send_tcp_transaction(
+    handle: &ModbusConnectionHandle,
+    unit_id: u8,
+    function_code: u8,
+    request_data: &[u8],
+) -> Result<TcpTransaction, String> {
+    let transaction_id = handle.transaction_id.fetch_add(1, Ordering::Relaxed);
+    let frame = build_tcp_frame(transaction_id, unit_id, function_code, request_data);
+    send_tcp_frame(handle, transaction_id, frame)
 }
 
-/// Kiểm tra trạng thái kết nối Modbus
-#[tauri::command]
-fn modbus_is_connected(state: State<ModbusState>, connection_id: String) -> bool {
-    let connections = state.connections.lock();
-    connections.contains_key(&connection_id)
+// Gửi 1 frame đã build qua TCP và đăng ký chờ response với key transaction_id
+fn send_tcp_frame(
+    handle: &ModbusConnectionHandle,
+    transaction_id: u16,
+    frame: Vec<u8>,
+) -> Result<TcpTransaction, String> {
+    let tx = handle.tcp_tx.as_ref()
+        .ok_or("TCP channel not available")?;
+    let pending = handle.tcp_pending.as_ref()
+        .ok_or("TCP response channel not available")?
+        .clone();
+
+    // Đăng ký trước khi gửi để không bỏ lỡ response đến nhanh
+    let (response_tx, response_rx) = std::sync::mpsc::channel();
+    pending.lock().insert(transaction_id, response_tx);
+
+    if tx.try_send(frame.clone()).is_err() {
+        pending.lock().remove(&transaction_id);
+        return Err("Failed to send request".to_string());
+    }
+
+    Ok(TcpTransaction {
+        transaction_id,
+        frame,
+        sent_at: Instant::now(),
+        response_rx,
+        pending,
+    })
 }
 
-/// Bắt đầu polling Modbus
+// Chờ response của transaction; hết hạn thì huỷ đăng ký để response đến muộn bị bỏ qua
+fn wait_tcp_transaction(transaction: &TcpTransaction, deadline: Instant) -> Result<Vec<u8>, String> {
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    match transaction.response_rx.recv_timeout(timeout) {
+        Ok(frame) => Ok(frame),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            transaction.pending.lock().remove(&transaction.transaction_id);
+            Err("Response timeout".to_string())
+        }
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => Err("Channel closed".to_string()),
+    }
+}
+
+/// Gửi nhiều request Modbus TCP liên tiếp không chờ (pipelining), rồi nhận response theo transaction ID.
+/// Request lỗi/timeout trả về response với success = false và error_message.
 #[tauri::command]
-fn modbus_start_polling(
+fn modbus_request_pipelined(
     app: AppHandle,
     state: State<ModbusState>,
-    config: ModbusPollConfig,
-) -> Result<String, String> {
-    let connections = state.connections.lock();
+    connection_id: String,
+    requests: Vec<ModbusRequest>,
+) -> Result<Vec<ModbusResponse>, String> {
+    let mut in_flight = Vec::with_capacity(requests.len());
+    let timeout_ms = {
+        let connections = state.connections.lock();
+        let handle = connections
+            .get(&connection_id)
+            .ok_or_else(|| format!("Connection {} không tồn tại", connection_id))?;
 
-    let handle = connections
-        .get(&config.connection_id)
-        .ok_or_else(|| format!("Connection {} không tồn tại", config.connection_id))?;
+        let (unit_id, timeout_ms) = match &handle.config {
+            ModbusConnectionConfig::Tcp { unit_id, response_timeout_ms, .. } => (*unit_id, *response_timeout_ms),
+            _ => return Err("Pipelining chỉ hỗ trợ Modbus TCP".to_string()),
+        };
 
-    if handle.polling_active.load(Ordering::Relaxed) {
-        return Err("Polling đã đang chạy".to_string());
-    }
+        for request in &requests {
+            let transaction = build_request_data(request).and_then(|data| {
+                send_tcp_transaction(handle, request.slave_id.unwrap_or(unit_id), request.function_code, &data)
+            });
+            in_flight.push(transaction);
+        }
+        timeout_ms
+    };
+
+    let responses = requests
+        .iter()
+        .zip(in_flight)
+        .map(|(request, transaction)| {
+            let request = ModbusRequest { connection_id: connection_id.clone(), ..request.clone() };
+            let result = transaction.and_then(|transaction| {
+                let deadline = transaction.sent_at + Duration::from_millis(timeout_ms as u64);
+                let response_frame = wait_tcp_transaction(&transaction, deadline)?;
+                let parsed = parse_tcp_response(&response_frame, request.function_code)?;
+                Ok(build_modbus_response(
+                    &request,
+                    transaction.transaction_id,
+                    transaction.frame,
+                    response_frame,
+                    parsed,
+                    transaction.sent_at.elapsed().as_millis() as u64,
+                ))
+            });
+
+            let response = result.unwrap_or_else(|e| ModbusResponse {
+                connection_id: connection_id.clone(),
+                transaction_id: 0,
+                slave_id: request.slave_id.unwrap_or(0),
+                function_code: request.function_code,
+                start_address: request.start_address,
+                quantity: request.quantity,
+                success: false,
+                data: None,
+                coils: None,
+                error_code: None,
+                error_message: Some(e),
+                request_frame: Vec::new(),
+                response_frame: Vec::new(),
+                response_time_ms: 0,
+                timestamp: modbus::get_timestamp(),
+                device_identification: None,
+                server_id: None,
+                decoded: None,
+            });
+            let _ = app.emit("modbus-response", response.clone());
+            response
+        })
+        .collect();
+
+    Ok(responses)
+}
+
+/// Kiểm tra trạng thái kết nối Modbus
+#[tauri::command]
+fn modbus_is_connected(state: State<ModbusState>, connection_id: String) -> bool {
+    let connections = state.connections.lock();
+    connections.contains_key(&connection_id)
+}
+
+/// Bắt đầu polling Modbus
+#[tauri::command]
+fn modbus_start_polling(
+    app: AppHandle,
+    state: State<ModbusState>,
+    config: ModbusPollConfig,
+) -> Result<String, String> {
+    let connections = state.connections.lock();
+
+    let handle = connections
+        .get(&config.connection_id)
+        .ok_or_else(|| format!("Connection {} không tồn tại", config.connection_id))?;
+
+    if handle.polling_active.load(Ordering::Relaxed) {
+        return Err("Polling đã đang chạy".to_string());
+    }
 
     handle.polling_active.store(true, Ordering::Relaxed);
 
@@ -1890,11 +3070,13 @@ fn modbus_start_polling(
             for poll_req in &requests {
                 let request = ModbusRequest {
                     connection_id: connection_id.clone(),
+                    slave_id: poll_req.slave_id,
                     function_code: poll_req.function_code,
                     start_address: poll_req.start_address,
                     quantity: poll_req.quantity,
                     values: None,
                     coil_values: None,
+                    ..Default::default()
                 };
 
                 // Build and send request - get data while holding lock, then release
@@ -1903,15 +3085,22 @@ fn modbus_start_polling(
                     if let Some(handle) = connections.get(&connection_id) {
                         if let Ok(request_data) = build_request_data(&request) {
                             let (slave_id, _timeout_ms) = match &handle.config {
-                                ModbusConnectionConfig::Rtu { slave_id, response_timeout_ms, .. } => {
+                                ModbusConnectionConfig::Rtu { slave_id, response_timeout_ms, .. }
+                                | ModbusConnectionConfig::RtuOverTcp { slave_id, response_timeout_ms, .. } => {
                                     (*slave_id, *response_timeout_ms)
                                 }
                                 ModbusConnectionConfig::Tcp { unit_id, response_timeout_ms, .. } => {
                                     (*unit_id, *response_timeout_ms)
                                 }
                             };
+                            let slave_id = request.slave_id.unwrap_or(slave_id);
 
                             match handle.mode {
+                                // ASCII và RTU over TCP: giữ lock trong suốt request
+                                ModbusMode::Ascii => execute_modbus_request(handle, &request, None).ok(),
+                                ModbusMode::Rtu if handle.serial_port.is_none() => {
+                                    execute_modbus_request(handle, &request, None).ok()
+                                }
                                 ModbusMode::Rtu => {
                                     if let Some(port) = &handle.serial_port {
                                         let frame = build_rtu_frame(slave_id, poll_req.function_code, &request_data);
@@ -1923,7 +3112,10 @@ fn modbus_start_polling(
                                         let mut buffer = [0u8; 256];
                                         if let Ok(n) = port.read(&mut buffer) {
                                             if n >= 5 {
-                                                if let Ok(parsed) = parse_rtu_response(&buffer[..n], poll_req.function_code) {
+                                                if let Some(parsed) = parse_rtu_response(&buffer[..n], poll_req.function_code)
+                                                    .ok()
+                                                    .filter(|p| p.slave_id == slave_id)
+                                                {
                                                     // Truncate coils to requested quantity
                                                     let truncated_coils = parsed.coils.map(|coils| {
                                                         coils.into_iter().take(poll_req.quantity as usize).collect()
@@ -1944,6 +3136,9 @@ fn modbus_start_polling(
                                                         response_frame: buffer[..n].to_vec(),
                                                         response_time_ms: 0,
                                                         timestamp: modbus::get_timestamp(),
+                                                        device_identification: parsed.device_identification,
+                                                        server_id: parsed.server_id,
+                                                        decoded: None,
                                                     })
                                                 } else {
                                                     None
@@ -1972,96 +3167,48 @@ fn modbus_start_polling(
                 }; // Lock released here
 
                 // Emit response outside of lock (for RTU)
-                if let Some(response) = poll_result {
+                if let Some(mut response) = poll_result {
+                    apply_decode(&mut response, poll_req.decode.as_ref());
                     let _ = app_clone.emit("modbus-poll-data", response);
                 } else {
                     // Handle TCP polling - need to send and wait for response outside of lock
                     let tcp_result: Option<ModbusResponse> = {
                         let connections = connections_clone.lock();
-                        if let Some(handle) = connections.get(&connection_id) {
-                            if handle.mode == ModbusMode::Tcp {
-                                if let (Some(tx), Some(rx_arc)) = (&handle.tcp_tx, &handle.tcp_rx) {
-                                    let tx = tx.clone();
-                                    let rx_arc = rx_arc.clone();
-                                    let transaction_id = handle.transaction_id.fetch_add(1, Ordering::Relaxed);
-                                    let (slave_id, timeout_ms) = match &handle.config {
-                                        ModbusConnectionConfig::Tcp { unit_id, response_timeout_ms, .. } => {
-                                            (*unit_id, *response_timeout_ms)
-                                        }
-                                        _ => (1, 1000),
-                                    };
-
-                                    if let Ok(request_data) = build_request_data(&request) {
-                                        let frame = build_tcp_frame(transaction_id, slave_id, poll_req.function_code, &request_data);
-                                        drop(connections); // Release lock before waiting
-
-                                        // Send request
-                                        if tx.try_send(frame.clone()).is_ok() {
-                                            // Wait for response with timeout
-                                            let start = std::time::Instant::now();
-                                            let timeout = Duration::from_millis(timeout_ms as u64);
-
-                                            loop {
-                                                if start.elapsed() > timeout {
-                                                    break None;
-                                                }
-
-                                                let mut rx = rx_arc.lock();
-                                                match rx.try_recv() {
-                                                    Ok(response_frame) => {
-                                                        drop(rx);
-                                                        if let Ok(parsed) = parse_tcp_response(&response_frame, poll_req.function_code) {
-                                                            // Truncate coils to requested quantity
-                                                            let truncated_coils = parsed.coils.map(|coils| {
-                                                                coils.into_iter().take(poll_req.quantity as usize).collect()
-                                                            });
-                                                            break Some(ModbusResponse {
-                                                                connection_id: connection_id.clone(),
-                                                                transaction_id,
-                                                                slave_id: parsed.slave_id,
-                                                                function_code: parsed.function_code,
-                                                                start_address: poll_req.start_address,
-                                                                quantity: poll_req.quantity,
-                                                                success: !parsed.is_exception,
-                                                                data: parsed.data,
-                                                                coils: truncated_coils,
-                                                                error_code: parsed.exception_code,
-                                                                error_message: parsed.exception_code.map(format_exception_error),
-                                                                request_frame: frame,
-                                                                response_frame,
-                                                                response_time_ms: start.elapsed().as_millis() as u64,
-                                                                timestamp: modbus::get_timestamp(),
-                                                            });
-                                                        } else {
-                                                            break None;
-                                                        }
-                                                    }
-                                                    Err(tokio::sync::mpsc::error::TryRecvError::Empty) => {
-                                                        drop(rx);
-                                                        std::thread::sleep(Duration::from_millis(10));
-                                                        continue;
-                                                    }
-                                                    Err(_) => break None,
-                                                }
-                                            }
-                                        } else {
-                                            None
-                                        }
-                                    } else {
-                                        None
+                        match connections.get(&connection_id) {
+                            Some(handle) if handle.mode == ModbusMode::Tcp => {
+                                let (slave_id, timeout_ms) = match &handle.config {
+                                    ModbusConnectionConfig::Tcp { unit_id, response_timeout_ms, .. } => {
+                                        (*unit_id, *response_timeout_ms)
                                     }
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
+                                    _ => (1, 1000),
+                                };
+                                let slave_id = request.slave_id.unwrap_or(slave_id);
+
+                                let transaction = build_request_data(&request).and_then(|data| {
+                                    send_tcp_transaction(handle, slave_id, poll_req.function_code, &data)
+                                });
+                                drop(connections); // Release lock before waiting
+
+                                transaction.ok().and_then(|transaction| {
+                                    let deadline = transaction.sent_at + Duration::from_millis(timeout_ms as u64);
+                                    let response_frame = wait_tcp_transaction(&transaction, deadline).ok()?;
+                                    let parsed = parse_tcp_response(&response_frame, poll_req.function_code).ok()?;
+                                    Some(build_modbus_response(
+                                        &request,
+                                        transaction.transaction_id,
+                                        transaction.frame,
+                                        response_frame,
+                                        parsed,
+                                        transaction.sent_at.elapsed().as_millis() as u64,
+                                    ))
+                                })
                             }
-                        } else {
-                            None
+                            _ => None,
                         }
                     };
 
-                    if let Some(response) = tcp_result {
+                    if let Some(mut response) = tcp_result {
+                        apply_decode(&mut response, poll_req.decode.as_ref());
                         let _ = app_clone.emit("modbus-poll-data", response);
                     }
                 }
@@ -2094,6 +3241,46 @@ fn modbus_stop_polling(
 
 // ===================== MODBUS SLAVE COMMANDS =====================
 
+/// Chạy simulation engine cho slave trên runtime của ModbusSlaveState.
+/// Engine gắn với cờ running của đúng handle: slave dừng rồi khởi động lại (handle mới) thì engine cũ kết thúc
+fn spawn_slave_simulation_engine(
+    app: AppHandle,
+    state: &ModbusSlaveState,
+    connection_id: String,
+    running: Arc<AtomicBool>,
+) {
+    let connections = state.connections.clone();
+
+    state.runtime.spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(SIMULATION_TICK_MS));
+        let mut runtimes: HashMap<(u8, String, u16), SimulationRuntime> = HashMap::new();
+
+        loop {
+            interval.tick().await;
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+
+            let events = {
+                let connections = connections.lock();
+                match connections.get(&connection_id) {
+                    Some(h) if Arc::ptr_eq(&h.running, &running) => {
+                        let mut events = h.run_simulations(&mut runtimes, &connection_id, modbus::get_timestamp());
+                        // Auto-clear của rule chạy theo cùng nhịp, dừng cùng slave
+                        events.extend(h.run_auto_clears(&connection_id, Instant::now()));
+                        events
+                    }
+                    _ => break,
+                }
+            };
+
+            for event in events {
+                let _ = app.emit("modbus-slave-data-changed", event);
+            }
+        }
+    });
+}
+
 /// Khởi động Modbus Slave RTU (Serial)
 #[tauri::command]
 fn modbus_slave_rtu_start(
@@ -2103,6 +3290,10 @@ fn modbus_slave_rtu_start(
 ) -> Result<String, String> {
     let connection_id = format!("modbus-slave-rtu-{}", config.port_name.replace("/", "_"));
 
+    if config.mode == ModbusMode::Tcp {
+        return Err("Serial chỉ hỗ trợ mode rtu hoặc ascii".to_string());
+    }
+
     // Kiểm tra xem connection đã tồn tại chưa
     {
         let connections = state.connections.lock();
@@ -2112,60 +3303,48 @@ fn modbus_slave_rtu_start(
     }
 
     // Parse serial config
-    let data_bits = match config.data_bits {
-        5 => DataBits::Five,
-        6 => DataBits::Six,
-        7 => DataBits::Seven,
-        8 => DataBits::Eight,
-        _ => return Err("Data bits không hợp lệ".to_string()),
-    };
+    let data_bits = parse_data_bits(config.data_bits)?;
+    let stop_bits = parse_stop_bits(&config.stop_bits)?;
+    let parity = parse_parity(&config.parity)?;
 
-    let stop_bits = match config.stop_bits.as_str() {
-        "1" => StopBits::One,
-        "1.5" => StopBits::Two,
-        "2" => StopBits::Two,
-        _ => return Err("Stop bits không hợp lệ".to_string()),
-    };
-
-    let parity = match config.parity.to_lowercase().as_str() {
-        "none" => Parity::None,
-        "odd" => Parity::Odd,
-        "even" => Parity::Even,
-        _ => return Err("Parity không hợp lệ".to_string()),
-    };
+    let flow_control = parse_flow_control(&config.flow_control)?;
 
     // Mở serial port
     let port = serialport::new(&config.port_name, config.baud_rate)
         .data_bits(data_bits)
         .stop_bits(stop_bits)
         .parity(parity)
-        .flow_control(FlowControl::None)
+        .flow_control(flow_control)
         .timeout(Duration::from_millis(10))
         .open()
         .map_err(|e| format!("Không thể mở port {}: {}", config.port_name, e))?;
 
     let slave_id = config.slave_id;
+    let mode = config.mode;
     let handle = ModbusSlaveHandle::new_rtu(config.clone());
-    let data = handle.data.clone();
 
     // Lưu connection
     let connection_id_clone = connection_id.clone();
     // Clone cho thread
     let connections_clone = state.connections.clone();
 
+    let running = handle.running.clone();
     {
         let mut connections = state.connections.lock();
         connections.insert(connection_id.clone(), handle);
     }
 
     // Emit status
+    let framing = if mode == ModbusMode::Ascii { "ASCII" } else { "RTU" };
     let _ = app.emit("modbus-slave-status", ModbusSlaveStatusEvent {
         connection_id: connection_id.clone(),
         status: "started".to_string(),
-        message: Some(format!("RTU {} [ID:{}]", config.port_name, slave_id)),
+        message: Some(format!("{} {} [ID:{}]", framing, config.port_name, slave_id)),
         timestamp: modbus::get_timestamp(),
     });
 
+    spawn_slave_simulation_engine(app.clone(), &state, connection_id.clone(), running);
+
     let app_clone = app.clone();
 
     // Spawn thread đọc và xử lý requests
@@ -2173,6 +3352,8 @@ fn modbus_slave_rtu_start(
         let mut port = port;
         let mut buffer = [0u8; 256];
         let mut frame_buffer: Vec<u8> = Vec::with_capacity(256);
+        // ASCII: 1 lần đọc có thể chứa nhiều frame, xử lý lần lượt
+        let mut ascii_frames: std::collections::VecDeque<Vec<u8>> = std::collections::VecDeque::new();
         let mut last_byte_time = Instant::now();
         let inter_frame_gap = Duration::from_micros(calculate_inter_frame_delay_us(config.baud_rate));
 
@@ -2191,101 +3372,150 @@ fn modbus_slave_rtu_start(
             }
 
             // Đọc data
-            match port.read(&mut buffer) {
-                Ok(n) if n > 0 => {
-                    frame_buffer.extend_from_slice(&buffer[..n]);
-                    last_byte_time = Instant::now();
+            let mut frame: Option<Vec<u8>> = ascii_frames.pop_front();
+            if frame.is_none() {
+                match port.read(&mut buffer) {
+                    Ok(n) if n > 0 => {
+                        frame_buffer.extend_from_slice(&buffer[..n]);
+                        last_byte_time = Instant::now();
+                        // ASCII: frame kết thúc bằng CRLF (không dựa vào khoảng lặng)
+                        if mode == ModbusMode::Ascii {
+                            while let Some(ascii_frame) = take_ascii_frame(&mut frame_buffer) {
+                                ascii_frames.push_back(ascii_frame);
+                            }
+                            frame = ascii_frames.pop_front();
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                        // RTU: frame hoàn chỉnh khi có khoảng lặng 3.5 ký tự
+                        if mode == ModbusMode::Rtu && !frame_buffer.is_empty() && last_byte_time.elapsed() > inter_frame_gap {
+                            frame = Some(std::mem::take(&mut frame_buffer));
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Serial read error: {}", e);
+                        break;
+                    }
                 }
-                Ok(_) => {}
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                    // Check if we have a complete frame (gap timeout)
-                    if !frame_buffer.is_empty() && last_byte_time.elapsed() > inter_frame_gap {
-                        // Try to parse and process frame
-                        if frame_buffer.len() >= 8 {
-                            if let Ok(request) = parse_rtu_request(&frame_buffer) {
-                                // Check if slave ID matches
-                                if request.slave_id == slave_id {
-                                    let start_time = Instant::now();
-
-                                    // Get handle for delay and exception checking
-                                    let delay_ms = {
-                                        let connections = connections_clone.lock();
-                                        if let Some(h) = connections.get(&connection_id_clone) {
-                                            h.get_delay_ms(request.function_code)
-                                        } else {
-                                            0
-                                        }
-                                    };
+            }
 
-                                    // Apply delay if configured
-                                    if delay_ms > 0 {
-                                        thread::sleep(Duration::from_millis(delay_ms as u64));
-                                    }
+            let Some(frame) = frame else {
+                continue;
+            };
 
-                                    // Process request
-                                    let result = {
-                                        let connections = connections_clone.lock();
-                                        if let Some(h) = connections.get(&connection_id_clone) {
-                                            process_request(
-                                                &request,
-                                                &data,
-                                                ModbusMode::Rtu,
-                                                0,
-                                                &connection_id_clone,
-                                                h,
-                                            )
-                                        } else {
-                                            continue;
-                                        }
-                                    };
+            // Try to parse and process frame
+            let parsed = match mode {
+                ModbusMode::Ascii => parse_ascii_request(&frame),
+                _ => parse_rtu_request(&frame),
+            };
+            let Ok(request) = parsed else {
+                continue;
+            };
 
-                                    let response_time = start_time.elapsed().as_millis() as u64;
+            // Broadcast (ID 0): ghi vào mọi slave mô phỏng, không gửi response
+            if request.slave_id == 0 {
+                let start_time = Instant::now();
+                let events = {
+                    let connections = connections_clone.lock();
+                    match connections.get(&connection_id_clone) {
+                        Some(h) if h.broadcast_enabled() && is_broadcast_function(request.function_code) => {
+                            h.apply_broadcast(&request, &connection_id_clone)
+                        }
+                        _ => continue,
+                    }
+                };
 
-                                    // Send response
-                                    if let Err(e) = port.write_all(&result.response_frame) {
-                                        eprintln!("Error sending response: {}", e);
-                                    }
-                                    let _ = port.flush();
-
-                                    // Emit request event
-                                    let _ = app_clone.emit("modbus-slave-request", ModbusSlaveRequestEvent {
-                                        connection_id: connection_id_clone.clone(),
-                                        client_id: None,
-                                        slave_id: request.slave_id,
-                                        function_code: request.function_code,
-                                        start_address: result.start_address,
-                                        quantity: result.quantity,
-                                        request_frame: frame_buffer.clone(),
-                                        response_frame: result.response_frame.clone(),
-                                        success: result.success,
-                                        error_message: result.error_message,
-                                        response_time_ms: response_time,
-                                        timestamp: modbus::get_timestamp(),
-                                    });
+                let _ = app_clone.emit("modbus-slave-request", ModbusSlaveRequestEvent {
+                    connection_id: connection_id_clone.clone(),
+                    client_id: None,
+                    slave_id: 0,
+                    function_code: request.function_code,
+                    start_address: request.start_address,
+                    quantity: request.quantity,
+                    request_frame: frame,
+                    response_frame: Vec::new(),
+                    success: true,
+                    error_message: None,
+                    response_time_ms: start_time.elapsed().as_millis() as u64,
+                    timestamp: modbus::get_timestamp(),
+                });
+                for event in events {
+                    let _ = app_clone.emit("modbus-slave-data-changed", event);
+                }
+                continue;
+            }
 
-                                    // Emit data changed if applicable
-                                    if let Some(event) = result.data_changed {
-                                        let _ = app_clone.emit("modbus-slave-data-changed", event);
-                                    }
+            // Multi-drop: chỉ trả lời các slave ID đang mô phỏng, ID khác im lặng như trên bus thật
+            let unit = {
+                let connections = connections_clone.lock();
+                connections
+                    .get(&connection_id_clone)
+                    .and_then(|h| h.unit(Some(request.slave_id)))
+            };
+            let Some(unit) = unit else {
+                continue;
+            };
 
-                                    // Update statistics
-                                    {
-                                        let connections = connections_clone.lock();
-                                        if let Some(h) = connections.get(&connection_id_clone) {
-                                            h.increment_request_count();
-                                            let mut stats = h.statistics.write();
-                                            stats.record_request(request.function_code, result.success, response_time);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        frame_buffer.clear();
-                    }
+            let start_time = Instant::now();
+
+            // Get delay of the addressed slave
+            let delay_ms = unit.get_delay_ms(request.function_code);
+
+            // Apply delay if configured
+            if delay_ms > 0 {
+                thread::sleep(Duration::from_millis(delay_ms as u64));
+            }
+
+            // Process request (rule của slave chạy cùng lúc, trước khi trả response)
+            let (result, rule_changes) = {
+                let connections = connections_clone.lock();
+                if let Some(h) = connections.get(&connection_id_clone) {
+                    unit.process(&request, mode, 0, &connection_id_clone, h)
+                } else {
+                    continue;
                 }
-                Err(e) => {
-                    eprintln!("Serial read error: {}", e);
-                    break;
+            };
+
+            let response_time = start_time.elapsed().as_millis() as u64;
+
+            // Send response
+            if let Err(e) = port.write_all(&result.response_frame) {
+                eprintln!("Error sending response: {}", e);
+            }
+            let _ = port.flush();
+
+            // Emit request event
+            let _ = app_clone.emit("modbus-slave-request", ModbusSlaveRequestEvent {
+                connection_id: connection_id_clone.clone(),
+                client_id: None,
+                slave_id: request.slave_id,
+                function_code: request.function_code,
+                start_address: result.start_address,
+                quantity: result.quantity,
+                request_frame: frame,
+                response_frame: result.response_frame.clone(),
+                success: result.success,
+                error_message: result.error_message,
+                response_time_ms: response_time,
+                timestamp: modbus::get_timestamp(),
+            });
+
+            // Emit data changed if applicable
+            if let Some(event) = result.data_changed {
+                let _ = app_clone.emit("modbus-slave-data-changed", event);
+            }
+            for event in rule_changes {
+                let _ = app_clone.emit("modbus-slave-data-changed", event);
+            }
+
+            // Update statistics
+            {
+                let connections = connections_clone.lock();
+                if let Some(h) = connections.get(&connection_id_clone) {
+                    h.increment_request_count();
+                    let mut stats = h.statistics.write();
+                    stats.record_request(request.function_code, result.success, response_time);
                 }
             }
         }
@@ -2325,17 +3555,21 @@ fn modbus_slave_tcp_start(
     }
 
     let unit_id = config.unit_id;
+    let rtu_over_tcp = config.rtu_over_tcp;
+    let frame_mode = if rtu_over_tcp { ModbusMode::Rtu } else { ModbusMode::Tcp };
     let handle = ModbusSlaveHandle::new_tcp(config.clone());
-    let data = handle.data.clone();
     let tcp_clients = handle.tcp_clients.clone();
 
     // Lưu connection
     let connection_id_clone = connection_id.clone();
+    let running = handle.running.clone();
     {
         let mut connections = state.connections.lock();
         connections.insert(connection_id.clone(), handle);
     }
 
+    spawn_slave_simulation_engine(app.clone(), &state, connection_id.clone(), running);
+
     let addr = format!("{}:{}", config.bind_address, config.listen_port);
     let app_clone = app.clone();
     let connections_clone = state.connections.clone();
@@ -2371,7 +3605,12 @@ fn modbus_slave_tcp_start(
         let _ = app_clone.emit("modbus-slave-status", ModbusSlaveStatusEvent {
             connection_id: connection_id_clone.clone(),
             status: "started".to_string(),
-            message: Some(format!("TCP {} [ID:{}]", addr, unit_id)),
+            message: Some(format!(
+                "{} {} [ID:{}]",
+                if rtu_over_tcp { "RTU over TCP" } else { "TCP" },
+                addr,
+                unit_id
+            )),
             timestamp: modbus::get_timestamp(),
         });
 
@@ -2422,7 +3661,6 @@ fn modbus_slave_tcp_start(
                             });
 
                             // Spawn client handler
-                            let data_ref = data.clone();
                             let app_ref = app_clone.clone();
                             let conn_id_ref = connection_id_clone.clone();
                             let client_id_ref = client_id.clone();
@@ -2433,8 +3671,9 @@ fn modbus_slave_tcp_start(
                             tokio::spawn(async move {
                                 let (mut read_half, mut write_half) = stream.into_split();
                                 let mut buffer = [0u8; 1024];
+                                let mut stream_buffer: Vec<u8> = Vec::new();
 
-                                loop {
+                                'client: loop {
                                     // Check if still running
                                     let should_continue = {
                                         let connections = connections_ref.lock();
@@ -2454,41 +3693,57 @@ fn modbus_slave_tcp_start(
                                             match result {
                                                 Ok(0) => break, // Client disconnected
                                                 Ok(n) => {
-                                                    let frame = &buffer[..n];
+                                                    // Ghép frame theo MBAP length: 1 lần đọc có thể chứa nửa request
+                                                    // hoặc nhiều request pipelined; trả lời lần lượt theo thứ tự
+                                                    stream_buffer.extend_from_slice(&buffer[..n]);
+                                                    loop {
+                                                        // RTU over TCP: không có MBAP, frame hoàn chỉnh khi CRC hợp lệ
+                                                        let (frame, parsed) = if rtu_over_tcp {
+                                                            let Some(frame) = take_rtu_frame(&mut stream_buffer) else { break };
+                                                            let parsed = parse_rtu_request(&frame).map(|request| (0, request));
+                                                            (frame, parsed)
+                                                        } else {
+                                                            let Some(frame) = take_mbap_frame(&mut stream_buffer) else { break };
+                                                            let parsed = parse_tcp_request(&frame);
+                                                            (frame, parsed)
+                                                        };
 
-                                                    // Parse TCP request
-                                                    if let Ok((transaction_id, request)) = parse_tcp_request(frame) {
-                                                        // Check unit ID
-                                                        if request.slave_id == unit_id {
+                                                        if let Ok((transaction_id, request)) = parsed {
                                                             let start_time = std::time::Instant::now();
 
-                                                            // Get delay
-                                                            let delay_ms = {
+                                                            // Unit của request: mỗi unit ID có data bank, exception và delay riêng
+                                                            let unit = {
                                                                 let connections = connections_ref.lock();
-                                                                if let Some(h) = connections.get(&conn_id_ref) {
-                                                                    h.get_delay_ms(request.function_code)
-                                                                } else {
-                                                                    0
-                                                                }
+                                                                connections.get(&conn_id_ref).and_then(|h| h.unit(Some(request.slave_id)))
                                                             };
 
+                                                            // Get delay
+                                                            let delay_ms = unit.as_ref().map_or(0, |u| u.get_delay_ms(request.function_code));
+
                                                             // Apply delay
                                                             if delay_ms > 0 {
                                                                 tokio::time::sleep(Duration::from_millis(delay_ms as u64)).await;
                                                             }
 
                                                             // Process request
-                                                            let result = {
+                                                            // Rule của slave chạy cùng lúc, trước khi trả response
+                                                            let (result, rule_changes) = {
                                                                 let connections = connections_ref.lock();
                                                                 if let Some(h) = connections.get(&conn_id_ref) {
-                                                                    process_request(
-                                                                        &request,
-                                                                        &data_ref,
-                                                                        ModbusMode::Tcp,
-                                                                        transaction_id,
-                                                                        &conn_id_ref,
-                                                                        h,
-                                                                    )
+                                                                    match &unit {
+                                                                        Some(unit) => unit.process(
+                                                                            &request,
+                                                                            frame_mode,
+                                                                            transaction_id,
+                                                                            &conn_id_ref,
+                                                                            h,
+                                                                        ),
+                                                                        // Unit ID không tồn tại: Gateway Target Device Failed to Respond
+                                                                        None => (
+                                                                            ProcessedRequest::exception(&request, 0x0B, frame_mode, transaction_id),
+                                                                            Vec::new(),
+                                                                        ),
+                                                                    }
                                                                 } else {
                                                                     continue;
                                                                 }
@@ -2497,8 +3752,8 @@ fn modbus_slave_tcp_start(
                                                             let response_time = start_time.elapsed().as_millis() as u64;
 
                                                             // Send response
-                                                            if let Err(_) = write_half.write_all(&result.response_frame).await {
-                                                                break;
+                                                            if write_half.write_all(&result.response_frame).await.is_err() {
+                                                                break 'client;
                                                             }
                                                             let _ = write_half.flush().await;
 
@@ -2522,6 +3777,9 @@ fn modbus_slave_tcp_start(
                                                             if let Some(event) = result.data_changed {
                                                                 let _ = app_ref.emit("modbus-slave-data-changed", event);
                                                             }
+                                                            for event in rule_changes {
+                                                                let _ = app_ref.emit("modbus-slave-data-changed", event);
+                                                            }
 
                                                             // Update statistics
                                                             {
@@ -2609,6 +3867,60 @@ fn modbus_slave_stop(
     }
 }
 
+// Lấy unit của slave (None = unit ID cấu hình khi start)
+fn slave_unit(handle: &ModbusSlaveHandle, unit_id: Option<u8>) -> Result<SlaveUnit, String> {
+    handle
+        .unit(unit_id)
+        .ok_or_else(|| format!("Unit {} không tồn tại", unit_id.unwrap_or_default()))
+}
+
+/// Thêm unit ID (data bank, exception, delay riêng) vào slave đang chạy
+#[tauri::command]
+fn modbus_slave_add_unit(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    unit_id: u8,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    handle.add_unit(unit_id)
+}
+
+/// Xoá unit ID đã thêm (không xoá được unit ID cấu hình khi start)
+#[tauri::command]
+fn modbus_slave_remove_unit(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    unit_id: u8,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    let removed = handle.units.write().remove(&unit_id);
+    removed
+        .map(|_| ())
+        .ok_or_else(|| format!("Unit {} không tồn tại", unit_id))
+}
+
+/// Danh sách unit ID slave đang trả lời
+#[tauri::command]
+fn modbus_slave_list_units(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+) -> Result<Vec<u8>, String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    Ok(handle.unit_ids())
+}
+
 /// Set coil value
 #[tauri::command]
 fn modbus_slave_set_coil(
@@ -2616,13 +3928,15 @@ fn modbus_slave_set_coil(
     connection_id: String,
     address: u16,
     value: bool,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut coils = handle.data.coils.write();
+    let mut coils = unit.data.coils.write();
     if (address as usize) < coils.len() {
         coils[address as usize] = value;
         Ok(())
@@ -2638,13 +3952,15 @@ fn modbus_slave_set_register(
     connection_id: String,
     address: u16,
     value: u16,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut registers = handle.data.holding_registers.write();
+    let mut registers = unit.data.holding_registers.write();
     if (address as usize) < registers.len() {
         registers[address as usize] = value;
         Ok(())
@@ -2660,13 +3976,15 @@ fn modbus_slave_set_discrete_input(
     connection_id: String,
     address: u16,
     value: bool,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut discrete = handle.data.discrete_inputs.write();
+    let mut discrete = unit.data.discrete_inputs.write();
     if (address as usize) < discrete.len() {
         discrete[address as usize] = value;
         Ok(())
@@ -2682,13 +4000,15 @@ fn modbus_slave_set_input_register(
     connection_id: String,
     address: u16,
     value: u16,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut registers = handle.data.input_registers.write();
+    let mut registers = unit.data.input_registers.write();
     if (address as usize) < registers.len() {
         registers[address as usize] = value;
         Ok(())
@@ -2705,18 +4025,20 @@ fn modbus_slave_get_data(
     data_type: String,
     start_address: u16,
     quantity: u16,
+    unit_id: Option<u8>,
 ) -> Result<Vec<u16>, String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
     let start = start_address as usize;
     let end = start + quantity as usize;
 
     match data_type.as_str() {
         "coils" => {
-            let coils = handle.data.coils.read();
+            let coils = unit.data.coils.read();
             if end <= coils.len() {
                 Ok(coils[start..end].iter().map(|&c| if c { 1 } else { 0 }).collect())
             } else {
@@ -2724,7 +4046,7 @@ fn modbus_slave_get_data(
             }
         }
         "discrete_inputs" => {
-            let discrete = handle.data.discrete_inputs.read();
+            let discrete = unit.data.discrete_inputs.read();
             if end <= discrete.len() {
                 Ok(discrete[start..end].iter().map(|&c| if c { 1 } else { 0 }).collect())
             } else {
@@ -2732,7 +4054,7 @@ fn modbus_slave_get_data(
             }
         }
         "holding_registers" => {
-            let registers = handle.data.holding_registers.read();
+            let registers = unit.data.holding_registers.read();
             if end <= registers.len() {
                 Ok(registers[start..end].to_vec())
             } else {
@@ -2740,7 +4062,7 @@ fn modbus_slave_get_data(
             }
         }
         "input_registers" => {
-            let registers = handle.data.input_registers.read();
+            let registers = unit.data.input_registers.read();
             if end <= registers.len() {
                 Ok(registers[start..end].to_vec())
             } else {
@@ -2757,13 +4079,15 @@ fn modbus_slave_set_delay(
     state: State<ModbusSlaveState>,
     connection_id: String,
     delay_config: ResponseDelayConfig,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    *handle.delay_config.write() = delay_config;
+    *unit.delay_config.write() = delay_config;
     Ok(())
 }
 
@@ -2773,13 +4097,15 @@ fn modbus_slave_set_exception(
     state: State<ModbusSlaveState>,
     connection_id: String,
     mapping: ExceptionMapping,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut mappings = handle.exception_mappings.write();
+    let mut mappings = unit.exception_mappings.write();
     mappings.push(mapping);
     Ok(())
 }
@@ -2791,17 +4117,170 @@ fn modbus_slave_clear_exception(
     connection_id: String,
     start_address: u16,
     end_address: u16,
+    unit_id: Option<u8>,
 ) -> Result<(), String> {
     let connections = state.connections.lock();
     let handle = connections
         .get(&connection_id)
         .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
 
-    let mut mappings = handle.exception_mappings.write();
+    let mut mappings = unit.exception_mappings.write();
     mappings.retain(|m| m.start_address != start_address || m.end_address != end_address);
     Ok(())
 }
 
+/// Set object table trả về cho FC43/14 (Read Device Identification)
+#[tauri::command]
+fn modbus_slave_set_device_identification(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    objects: Vec<DeviceIdObject>,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    if objects.iter().any(|o| o.value.len() > 244) {
+        return Err("Giá trị object tối đa 244 bytes".to_string());
+    }
+
+    *handle.device_identification.write() = objects
+        .into_iter()
+        .map(|o| DeviceIdObject {
+            name: device_id_object_name(o.id),
+            ..o
+        })
+        .collect();
+    Ok(())
+}
+
+/// Add (or replace) a register simulation
+#[tauri::command]
+fn modbus_slave_add_simulation(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    simulation: SimulationConfig,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    validate_simulation(&simulation)?;
+    slave_unit(handle, simulation.unit_id)?;
+
+    // Mỗi (unit, data type, address) chỉ có 1 simulation
+    let primary_id = handle.get_slave_id();
+    let unit_id = simulation.unit_id.unwrap_or(primary_id);
+    let mut simulations = handle.simulations.write();
+    simulations.retain(|s| {
+        s.unit_id.unwrap_or(primary_id) != unit_id
+            || s.data_type != simulation.data_type
+            || s.address != simulation.address
+    });
+    simulations.push(simulation);
+    Ok(())
+}
+
+/// Remove a register simulation
+#[tauri::command]
+fn modbus_slave_remove_simulation(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    data_type: String,
+    address: u16,
+    unit_id: Option<u8>,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    let primary_id = handle.get_slave_id();
+    let unit_id = unit_id.unwrap_or(primary_id);
+    let mut simulations = handle.simulations.write();
+    simulations.retain(|s| {
+        s.unit_id.unwrap_or(primary_id) != unit_id || s.data_type != data_type || s.address != address
+    });
+    Ok(())
+}
+
+/// List register simulations
+#[tauri::command]
+fn modbus_slave_list_simulations(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+) -> Result<Vec<SimulationConfig>, String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+
+    let simulations = handle.simulations.read().clone();
+    Ok(simulations)
+}
+
+/// Add a data-change rule (on-write, mirror, auto-clear) to a unit
+#[tauri::command]
+fn modbus_slave_add_rule(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    rule: SlaveRule,
+    unit_id: Option<u8>,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
+
+    validate_rule(&rule)?;
+
+    unit.rules.write().push(rule);
+    Ok(())
+}
+
+/// Remove a data-change rule by its index in the list
+#[tauri::command]
+fn modbus_slave_remove_rule(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    index: usize,
+    unit_id: Option<u8>,
+) -> Result<(), String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
+
+    let mut rules = unit.rules.write();
+    if index >= rules.len() {
+        return Err(format!("Rule {} không tồn tại", index));
+    }
+    rules.remove(index);
+    Ok(())
+}
+
+/// List data-change rules of a unit
+#[tauri::command]
+fn modbus_slave_list_rules(
+    state: State<ModbusSlaveState>,
+    connection_id: String,
+    unit_id: Option<u8>,
+) -> Result<Vec<SlaveRule>, String> {
+    let connections = state.connections.lock();
+    let handle = connections
+        .get(&connection_id)
+        .ok_or_else(|| format!("Slave {} không tồn tại", connection_id))?;
+    let unit = slave_unit(handle, unit_id)?;
+
+    let rules = unit.rules.read().clone();
+    Ok(rules)
+}
+
 /// Get slave statistics
 #[tauri::command]
 fn modbus_slave_get_stats(
@@ -2883,6 +4362,567 @@ fn modbus_slave_load_data(
     Ok(())
 }
 
+// ===================== MODBUS GATEWAY COMMANDS =====================
+
+/// Khởi động gateway Modbus TCP -> RTU: nhận request từ TCP master, chuyển sang serial
+#[tauri::command]
+fn modbus_gateway_start(
+    app: AppHandle,
+    state: State<ModbusGatewayState>,
+    config: ModbusGatewayConfig,
+) -> Result<String, String> {
+    let gateway_id = format!("modbus-gateway-{}:{}", config.bind_address, config.listen_port);
+
+    if state.running.lock().contains_key(&gateway_id) {
+        return Err(format!("Gateway {} đã đang chạy", gateway_id));
+    }
+
+    // Mở serial port (timeout ngắn, vòng đọc tự kiểm tra deadline)
+    let port = serialport::new(&config.port_name, config.baud_rate)
+        .data_bits(parse_data_bits(config.data_bits)?)
+        .stop_bits(parse_stop_bits(&config.stop_bits)?)
+        .parity(parse_parity(&config.parity)?)
+        .flow_control(parse_flow_control(&config.flow_control)?)
+        .timeout(Duration::from_millis(10))
+        .open()
+        .map_err(|e| format!("Không thể mở port {}: {}", config.port_name, e))?;
+    // Mutex trên port: các request từ nhiều client được gửi lần lượt lên bus
+    let port = Arc::new(Mutex::new(port));
+
+    let running = Arc::new(AtomicBool::new(true));
+    state.running.lock().insert(gateway_id.clone(), running.clone());
+
+    let addr = format!("{}:{}", config.bind_address, config.listen_port);
+    let timeout = Duration::from_millis(config.response_timeout_ms as u64);
+    let broadcast_delay = Duration::from_millis(config.broadcast_delay_ms as u64);
+    let gateway_id_clone = gateway_id.clone();
+
+    state.runtime.spawn(async move {
+        let emit_status = |status: &str, message: Option<String>| {
+            let _ = app.emit("modbus-gateway-status", ModbusConnectionStatus {
+                connection_id: gateway_id_clone.clone(),
+                status: status.to_string(),
+                message,
+                timestamp: modbus::get_timestamp(),
+            });
+        };
+
+        let listener = match TcpListener::bind(&addr).await {
+            Ok(l) => l,
+            Err(e) => {
+                let gateway_state = app.state::<ModbusGatewayState>();
+                let mut gateways = gateway_state.running.lock();
+                if gateways.get(&gateway_id_clone).is_some_and(|r| Arc::ptr_eq(r, &running)) {
+                    gateways.remove(&gateway_id_clone);
+                }
+                drop(gateways);
+                emit_status("error", Some(format!("Không thể bind {}: {}", addr, e)));
+                return;
+            }
+        };
+
+        emit_status("started", Some(format!("TCP {} -> RTU {}", addr, config.port_name)));
+
+        let mut client_counter: u32 = 0;
+        while running.load(Ordering::SeqCst) {
+            tokio::select! {
+                result = listener.accept() => {
+                    let Ok((stream, remote_addr)) = result else {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    };
+                    client_counter += 1;
+                    let client_id = format!("client-{}", client_counter);
+                    let app = app.clone();
+                    let port = port.clone();
+                    let running = running.clone();
+                    let gateway_id = gateway_id_clone.clone();
+
+                    emit_status("client_connected", Some(format!("{} ({})", client_id, remote_addr)));
+
+                    tokio::spawn(async move {
+                        let (mut read_half, mut write_half) = stream.into_split();
+                        let mut buffer = [0u8; 1024];
+                        let mut stream_buffer: Vec<u8> = Vec::new();
+
+                        'client: while running.load(Ordering::SeqCst) {
+                            tokio::select! {
+                                result = read_half.read(&mut buffer) => {
+                                    let n = match result {
+                                        Ok(0) | Err(_) => break,
+                                        Ok(n) => n,
+                                    };
+                                    stream_buffer.extend_from_slice(&buffer[..n]);
+
+                                    while let Some(tcp_request) = take_mbap_frame(&mut stream_buffer) {
+                                        let function_code = tcp_request.get(7).copied().unwrap_or(0);
+                                        let (transaction_id, unit_id, rtu_request) = match mbap_to_rtu(&tcp_request) {
+                                            Ok(translated) => translated,
+                                            Err(e) => {
+                                                // Không chuyển tiếp được: báo lỗi cho UI, không trả lời master
+                                                let _ = app.emit("modbus-gateway-frame", ModbusGatewayFrameEvent {
+                                                    gateway_id: gateway_id.clone(),
+                                                    client_id: client_id.clone(),
+                                                    transaction_id: u16::from_be_bytes([tcp_request[0], tcp_request[1]]),
+                                                    unit_id: tcp_request[6],
+                                                    function_code,
+                                                    tcp_request,
+                                                    rtu_request: Vec::new(),
+                                                    rtu_response: None,
+                                                    tcp_response: Vec::new(),
+                                                    exception_code: None,
+                                                    error: Some(e),
+                                                    response_time_ms: 0,
+                                                    timestamp: modbus::get_timestamp(),
+                                                });
+                                                continue;
+                                            }
+                                        };
+                                        let start_time = Instant::now();
+
+                                        // Serial là blocking: chạy trên thread riêng, chờ lock port
+                                        let exchange_port = port.clone();
+                                        let exchange_request = rtu_request.clone();
+                                        let exchange = tokio::task::spawn_blocking(move || {
+                                            let mut port = exchange_port.lock();
+                                            rtu_exchange(port.as_mut(), &exchange_request, timeout, broadcast_delay)
+                                        })
+                                        .await
+                                        .unwrap_or(Err(EXCEPTION_GATEWAY_PATH_UNAVAILABLE));
+
+                                        let (rtu_response, tcp_response, exception_code) = match exchange {
+                                            // Broadcast: không có response cho master
+                                            Ok(None) => (None, Vec::new(), None),
+                                            Ok(Some(rtu_response)) => match rtu_to_mbap(transaction_id, &rtu_response) {
+                                                Some(tcp_response) => (Some(rtu_response), tcp_response, None),
+                                                None => (
+                                                    Some(rtu_response),
+                                                    gateway_exception(transaction_id, unit_id, function_code, EXCEPTION_GATEWAY_TARGET_FAILED),
+                                                    Some(EXCEPTION_GATEWAY_TARGET_FAILED),
+                                                ),
+                                            },
+                                            Err(code) => (
+                                                None,
+                                                gateway_exception(transaction_id, unit_id, function_code, code),
+                                                Some(code),
+                                            ),
+                                        };
+
+                                        if !tcp_response.is_empty() {
+                                            if write_half.write_all(&tcp_response).await.is_err() {
+                                                break 'client;
+                                            }
+                                            let _ = write_half.flush().await;
+                                        }
+
+                                        let _ = app.emit("modbus-gateway-frame", ModbusGatewayFrameEvent {
+                                            gateway_id: gateway_id.clone(),
+                                            client_id: client_id.clone(),
+                                            transaction_id,
+                                            unit_id,
+                                            function_code,
+                                            tcp_request,
+                                            rtu_request,
+                                            rtu_response,
+                                            tcp_response,
+                                            exception_code,
+                                            error: None,
+                                            response_time_ms: start_time.elapsed().as_millis() as u64,
+                                            timestamp: modbus::get_timestamp(),
+                                        });
+                                    }
+                                }
+                                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+                            }
+                        }
+
+                        let _ = app.emit("modbus-gateway-status", ModbusConnectionStatus {
+                            connection_id: gateway_id,
+                            status: "client_disconnected".to_string(),
+                            message: Some(client_id),
+                            timestamp: modbus::get_timestamp(),
+                        });
+                    });
+                }
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            }
+        }
+
+        emit_status("stopped", None);
+    });
+
+    Ok(gateway_id)
+}
+
+/// Dừng gateway
+#[tauri::command]
+fn modbus_gateway_stop(state: State<ModbusGatewayState>, gateway_id: String) -> Result<(), String> {
+    let running = state
+        .running
+        .lock()
+        .remove(&gateway_id)
+        .ok_or_else(|| format!("Gateway {} không tồn tại", gateway_id))?;
+    running.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+// ===================== MODBUS SNIFFER COMMANDS =====================
+
+/// Bắt đầu giám sát bus RTU (thụ động, không bao giờ ghi lên port)
+#[tauri::command]
+fn modbus_sniff_start(
+    app: AppHandle,
+    state: State<ModbusSnifferState>,
+    config: ModbusSniffConfig,
+) -> Result<String, String> {
+    let sniffer_id = format!("modbus-sniff-{}", config.port_name);
+
+    if state.running.lock().contains_key(&sniffer_id) {
+        return Err(format!("Sniffer {} đã đang chạy", sniffer_id));
+    }
+
+    // Timeout đọc ngắn để phát hiện khoảng lặng 3.5 ký tự
+    let mut port = serialport::new(&config.port_name, config.baud_rate)
+        .data_bits(parse_data_bits(config.data_bits)?)
+        .stop_bits(parse_stop_bits(&config.stop_bits)?)
+        .parity(parse_parity(&config.parity)?)
+        .flow_control(parse_flow_control(&config.flow_control)?)
+        .timeout(Duration::from_millis(1))
+        .open()
+        .map_err(|e| format!("Không thể mở port {}: {}", config.port_name, e))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    state.running.lock().insert(sniffer_id.clone(), running.clone());
+
+    let inter_frame = Duration::from_micros(calculate_inter_frame_delay_us(config.baud_rate));
+    let response_timeout = Duration::from_millis(config.response_timeout_ms as u64);
+    let sniffer_id_clone = sniffer_id.clone();
+
+    thread::spawn(move || {
+        let emit_status = |status: &str, message: Option<String>| {
+            let _ = app.emit("modbus-sniff-status", ModbusConnectionStatus {
+                connection_id: sniffer_id_clone.clone(),
+                status: status.to_string(),
+                message,
+                timestamp: modbus::get_timestamp(),
+            });
+        };
+        emit_status("started", Some(format!("{} @ {} baud", config.port_name, config.baud_rate)));
+
+        let mut decoder = ModbusSniffDecoder::new(sniffer_id_clone.clone(), response_timeout);
+        let mut framer = SniffFramer::default();
+        let mut buffer = [0u8; 512];
+        let mut frame: Vec<u8> = Vec::with_capacity(256);
+        let mut frame_start = Instant::now();
+        let mut last_byte_time = Instant::now();
+        let mut previous_frame_end: Option<Instant> = None;
+
+        while running.load(Ordering::SeqCst) {
+            match port.read(&mut buffer) {
+                Ok(n) if n > 0 => {
+                    let now = Instant::now();
+                    if frame.is_empty() {
+                        frame_start = now;
+                    }
+                    frame.extend_from_slice(&buffer[..n]);
+                    last_byte_time = now;
+                }
+                Ok(_) => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => {
+                    emit_status("error", Some(format!("Lỗi đọc port: {}", e)));
+                    break;
+                }
+            }
+
+            // Hết frame khi bus im lặng >= 3.5 ký tự (hoặc vượt độ dài ADU tối đa)
+            if !frame.is_empty() && (last_byte_time.elapsed() >= inter_frame || frame.len() > 256) {
+                // Adapter USB gửi theo lô: framer ghép frame bị cắt và tách frame bị dính
+                for (frame, started_at, ended_at) in framer.push(&frame, frame_start, last_byte_time) {
+                    let gap_us = previous_frame_end
+                        .map(|end| started_at.saturating_duration_since(end).as_micros() as u64);
+                    let event = decoder.decode(&frame, started_at, ended_at, gap_us);
+                    let _ = app.emit("modbus-sniff", event);
+                    previous_frame_end = Some(ended_at);
+                }
+                frame.clear();
+            }
+        }
+
+        let sniffer_state = app.state::<ModbusSnifferState>();
+        let mut sniffers = sniffer_state.running.lock();
+        if sniffers.get(&sniffer_id_clone).is_some_and(|r| Arc::ptr_eq(r, &running)) {
+            sniffers.remove(&sniffer_id_clone);
+        }
+        drop(sniffers);
+
+        emit_status("stopped", None);
+    });
+
+    Ok(sniffer_id)
+}
+
+/// Dừng sniffer
+#[tauri::command]
+fn modbus_sniff_stop(state: State<ModbusSnifferState>, sniffer_id: String) -> Result<(), String> {
+    let running = state
+        .running
+        .lock()
+        .remove(&sniffer_id)
+        .ok_or_else(|| format!("Sniffer {} không tồn tại", sniffer_id))?;
+    running.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+// ===================== RECORDER COMMANDS =====================
+
+/// Bắt đầu ghi session (serial port, TCP connection/server, MQTT connection) ra file
+#[tauri::command]
+fn recorder_start(
+    app: AppHandle,
+    state: State<RecorderState>,
+    config: RecordingConfig,
+) -> Result<RecordingStatus, String> {
+    // Lỗi ghi file (ví dụ đầy đĩa) dừng session: báo cho frontend
+    state.start(config, Box::new(move |status| {
+        let _ = app.emit("recorder-status", status);
+    }))
+}
+
+/// Dừng ghi session
+#[tauri::command]
+fn recorder_stop(
+    state: State<RecorderState>,
+    connection_id: String,
+) -> Result<RecordingStatus, String> {
+    state.stop(&connection_id)
+}
+
+/// Danh sách các session đang ghi
+#[tauri::command]
+fn recorder_list(state: State<RecorderState>) -> Vec<RecordingStatus> {
+    state.list()
+}
+
+// Gửi 1 bản ghi replay qua đúng command send của target
+fn send_replay_chunk(app: &AppHandle, config: &ReplayConfig, data: &[u8]) -> Result<String, String> {
+    let hex: String = data.iter().map(|b| format!("{:02X}", b)).collect();
+
+    match config.target.as_str() {
+        "serial" => send_data(
+            app.state::<SerialState>(),
+            app.state::<RecorderState>(),
+            config.target_id.clone(),
+            hex,
+            true,
+            None,
+        ),
+        "tcp_client" => tcp_client_send(
+            app.state::<TcpState>(),
+            app.state::<RecorderState>(),
+            config.target_id.clone(),
+            hex,
+            true,
+        ),
+        "tcp_server" => tcp_server_send(
+            app.state::<TcpState>(),
+            app.state::<RecorderState>(),
+            config.target_id.clone(),
+            config.client_id.clone(),
+            hex,
+            true,
+        ),
+        other => Err(format!("Target không hợp lệ: {}", other)),
+    }
+}
+
+/// Replay file capture vào serial port / TCP client / TCP server
+#[tauri::command]
+fn replay_start(
+    app: AppHandle,
+    state: State<ReplayState>,
+    config: ReplayConfig,
+) -> Result<String, String> {
+    if !config.speed.is_finite() || config.speed <= 0.0 {
+        return Err("Speed phải lớn hơn 0".to_string());
+    }
+    if !["serial", "tcp_client", "tcp_server"].contains(&config.target.as_str()) {
+        return Err(format!("Target không hợp lệ: {}", config.target));
+    }
+
+    let records = load_capture(&config.file_path)?;
+    let steps = build_replay_steps(&records, config.speed, config.tx_only)?;
+    if steps.is_empty() {
+        return Err("File không có dữ liệu để replay".to_string());
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    {
+        let mut replays = state.running.lock();
+        if replays.contains_key(&config.replay_id) {
+            return Err(format!("Replay {} đang chạy", config.replay_id));
+        }
+        replays.insert(config.replay_id.clone(), running.clone());
+    }
+
+    let total = steps.len();
+    let replay_id = config.replay_id.clone();
+
+    thread::spawn(move || {
+        let emit_status = |status: &str, sent: u64, message: Option<String>| {
+            let _ = app.emit("replay-status", ReplayStatus {
+                replay_id: config.replay_id.clone(),
+                status: status.to_string(),
+                sent,
+                total,
+                message,
+            });
+        };
+
+        emit_status("running", 0, None);
+        let mut sent: u64 = 0;
+        let mut error: Option<String> = None;
+        let loop_delay = loop_restart_delay(&steps);
+
+        'replay: loop {
+            for (i, step) in steps.iter().enumerate() {
+                // Bản ghi đầu gửi ngay, các vòng lặp sau chờ loop_delay để không dồn dập target
+                let mut remaining = match i {
+                    0 if sent == 0 => Duration::ZERO,
+                    0 => loop_delay,
+                    _ => step.delay,
+                };
+                while !remaining.is_zero() {
+                    if !running.load(Ordering::SeqCst) {
+                        break 'replay;
+                    }
+                    let slice = remaining.min(Duration::from_millis(50));
+                    thread::sleep(slice);
+                    remaining -= slice;
+                }
+                if !running.load(Ordering::SeqCst) {
+                    break 'replay;
+                }
+
+                if let Err(e) = send_replay_chunk(&app, &config, &step.data) {
+                    error = Some(e);
+                    break 'replay;
+                }
+                sent += 1;
+            }
+
+            if !config.loop_playback {
+                break;
+            }
+            emit_status("running", sent, None);
+        }
+
+        let stopped = !running.load(Ordering::SeqCst);
+        {
+            let replay_state = app.state::<ReplayState>();
+            let mut replays = replay_state.running.lock();
+            if replays.get(&config.replay_id).is_some_and(|r| Arc::ptr_eq(r, &running)) {
+                replays.remove(&config.replay_id);
+            }
+        }
+
+        match error {
+            Some(e) => emit_status("error", sent, Some(e)),
+            None if stopped => emit_status("stopped", sent, None),
+            None => emit_status("finished", sent, None),
+        }
+    });
+
+    Ok(format!("Replay {} bắt đầu ({} bản ghi)", replay_id, total))
+}
+
+/// Dừng replay
+#[tauri::command]
+fn replay_stop(state: State<ReplayState>, replay_id: String) -> Result<String, String> {
+    let running = state
+        .running
+        .lock()
+        .remove(&replay_id)
+        .ok_or_else(|| format!("Replay {} không tồn tại", replay_id))?;
+
+    running.store(false, Ordering::SeqCst);
+    Ok(format!("Đã dừng replay {}", replay_id))
+}
+
+// ===================== SCRIPT COMMANDS =====================
+
+/// Chạy script Rhai (send/expect trên serial, TCP, Modbus)
+#[tauri::command]
+fn script_run(
+    app: AppHandle,
+    state: State<ScriptState>,
+    script_id: String,
+    source: String,
+) -> Result<String, String> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let engine = create_engine(app.clone(), script_id.clone(), stop.clone());
+
+    // Báo lỗi cú pháp ngay khi gọi
+    let ast = engine
+        .compile(&source)
+        .map_err(|e| format!("Lỗi cú pháp: {}", e))?;
+
+    {
+        let mut running = state.running.lock();
+        if running.contains_key(&script_id) {
+            return Err(format!("Script {} đang chạy", script_id));
+        }
+        running.insert(script_id.clone(), stop.clone());
+    }
+
+    let id = script_id.clone();
+    thread::spawn(move || {
+        let emit_status = |status: &str, message: Option<String>| {
+            let _ = app.emit("script-status", ScriptStatus {
+                script_id: id.clone(),
+                status: status.to_string(),
+                message,
+            });
+        };
+
+        emit_status("running", None);
+        let result = engine.run_ast(&ast);
+
+        {
+            let script_state = app.state::<ScriptState>();
+            let mut running = script_state.running.lock();
+            if running.get(&id).is_some_and(|r| Arc::ptr_eq(r, &stop)) {
+                running.remove(&id);
+            }
+            // Không giữ data cũ cho lần chạy sau
+            if running.is_empty() {
+                script_state.clear_all_inputs();
+            }
+        }
+
+        match result {
+            _ if stop.load(Ordering::SeqCst) => emit_status("stopped", None),
+            Ok(_) => emit_status("finished", None),
+            Err(e) => emit_status("error", Some(e.to_string())),
+        }
+    });
+
+    Ok(format!("Script {} bắt đầu", script_id))
+}
+
+/// Dừng script đang chạy
+#[tauri::command]
+fn script_stop(state: State<ScriptState>, script_id: String) -> Result<String, String> {
+    let stop = state
+        .running
+        .lock()
+        .remove(&script_id)
+        .ok_or_else(|| format!("Script {} không tồn tại", script_id))?;
+
+    stop.store(true, Ordering::SeqCst);
+    Ok(format!("Đã dừng script {}", script_id))
+}
+
 // ===================== MQTT COMMANDS =====================
 
 /// Connect to MQTT broker
@@ -3019,6 +5059,8 @@ async fn mqtt_publish(
 
     publish_message(&client, &topic, payload_bytes.clone(), qos, retain).await?;
 
+    app.state::<RecorderState>().record(&connection_id, "tx", &payload_bytes, None, Some(&topic));
+
     // Emit TX message to frontend
     let _ = app.emit(
         "mqtt-data",
@@ -3109,13 +5151,26 @@ pub fn run() {
         .manage(TcpState::default())
         .manage(ModbusState::default())
         .manage(ModbusSlaveState::default())
+        .manage(ModbusGatewayState::default())
+        .manage(ModbusSnifferState::default())
         .manage(MqttState::default())
+        .manage(RecorderState::default())
+        .manage(ReplayState::default())
+        .manage(ScriptState::default())
         .invoke_handler(tauri::generate_handler![
             // Serial commands
             list_serial_ports,
+            start_port_watcher,
+            stop_port_watcher,
+            set_port_auto_reopen,
             open_port,
             close_port,
+            reconfigure_port,
+            autodetect_baud,
             send_data,
+            set_dtr,
+            set_rts,
+            send_break,
             is_port_open,
             // TCP Client commands
             tcp_client_connect,
@@ -3135,13 +5190,24 @@ pub fn run() {
             modbus_tcp_connect,
             modbus_disconnect,
             modbus_request,
+            modbus_request_pipelined,
             modbus_is_connected,
             modbus_start_polling,
             modbus_stop_polling,
+            modbus_scan,
+            modbus_scan_stop,
+            modbus_map_scan,
             // Modbus Slave commands
             modbus_slave_rtu_start,
             modbus_slave_tcp_start,
             modbus_slave_stop,
+            modbus_gateway_start,
+            modbus_gateway_stop,
+            modbus_sniff_start,
+            modbus_sniff_stop,
+            modbus_slave_add_unit,
+            modbus_slave_remove_unit,
+            modbus_slave_list_units,
             modbus_slave_set_coil,
             modbus_slave_set_register,
             modbus_slave_set_discrete_input,
@@ -3150,6 +5216,13 @@ pub fn run() {
             modbus_slave_set_delay,
             modbus_slave_set_exception,
             modbus_slave_clear_exception,
+            modbus_slave_set_device_identification,
+            modbus_slave_add_simulation,
+            modbus_slave_remove_simulation,
+            modbus_slave_list_simulations,
+            modbus_slave_add_rule,
+            modbus_slave_remove_rule,
+            modbus_slave_list_rules,
             modbus_slave_get_stats,
             modbus_slave_reset_stats,
             modbus_slave_is_running,
@@ -3162,7 +5235,16 @@ pub fn run() {
             mqtt_unsubscribe,
             mqtt_publish,
             mqtt_is_connected,
-            mqtt_export_messages
+            mqtt_export_messages,
+            // Recorder commands
+            recorder_start,
+            recorder_stop,
+            recorder_list,
+            replay_start,
+            replay_stop,
+            // Script commands
+            script_run,
+            script_stop
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
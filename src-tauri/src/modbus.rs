@@ -42,6 +42,12 @@ const CRC_TABLE: [u16; 256] = [
     0x8201, 0x42C0, 0x4380, 0x8341, 0x4100, 0x81C1, 0x8081, 0x4040,
 ];
 
+/// Longest Modbus ASCII frame: ':' + 2 x 255 hex chars + CRLF
+pub const MAX_ASCII_FRAME_LEN: usize = 513;
+
+/// Largest MBAP length field: unit ID (1) + PDU (253)
+pub const MAX_MBAP_LENGTH: usize = 254;
+
 // ===================== ENUMS =====================
 
 /// Modbus connection mode
@@ -50,6 +56,8 @@ const CRC_TABLE: [u16; 256] = [
 pub enum ModbusMode {
     Rtu,
     Tcp,
+    /// Modbus ASCII: ':' + hex + LRC + CRLF
+    Ascii,
 }
 
 /// Modbus function codes
@@ -62,8 +70,14 @@ pub enum FunctionCode {
     ReadInputRegisters = 0x04,
     WriteSingleCoil = 0x05,
     WriteSingleRegister = 0x06,
+    ReadExceptionStatus = 0x07,
+    Diagnostics = 0x08,
     WriteMultipleCoils = 0x0F,
     WriteMultipleRegisters = 0x10,
+    ReportServerId = 0x11,
+    MaskWriteRegister = 0x16,
+    ReadWriteMultipleRegisters = 0x17,
+    ReadDeviceIdentification = 0x2B, // MEI type 0x0E
 }
 
 impl FunctionCode {
@@ -75,8 +89,14 @@ impl FunctionCode {
             0x04 => Some(FunctionCode::ReadInputRegisters),
             0x05 => Some(FunctionCode::WriteSingleCoil),
             0x06 => Some(FunctionCode::WriteSingleRegister),
+            0x07 => Some(FunctionCode::ReadExceptionStatus),
+            0x08 => Some(FunctionCode::Diagnostics),
             0x0F => Some(FunctionCode::WriteMultipleCoils),
             0x10 => Some(FunctionCode::WriteMultipleRegisters),
+            0x11 => Some(FunctionCode::ReportServerId),
+            0x16 => Some(FunctionCode::MaskWriteRegister),
+            0x17 => Some(FunctionCode::ReadWriteMultipleRegisters),
+            0x2B => Some(FunctionCode::ReadDeviceIdentification),
             _ => None,
         }
     }
@@ -104,6 +124,51 @@ impl FunctionCode {
     }
 }
 
+/// Data type for typed register decoding
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegisterDataType {
+    U16,
+    I16,
+    U32,
+    I32,
+    F32,
+    U64,
+    F64,
+    String,
+    Bcd,
+}
+
+/// Longest BCD field: 4 registers = 16 digits (fits in u64)
+pub const MAX_BCD_REGISTERS: usize = 4;
+
+impl RegisterDataType {
+    /// Number of registers used by the type (`length` for string/BCD)
+    pub fn register_count(&self, length: u16) -> usize {
+        match self {
+            RegisterDataType::U16 | RegisterDataType::I16 => 1,
+            RegisterDataType::U32 | RegisterDataType::I32 | RegisterDataType::F32 => 2,
+            RegisterDataType::U64 | RegisterDataType::F64 => 4,
+            RegisterDataType::String | RegisterDataType::Bcd => length.max(1) as usize,
+        }
+    }
+}
+
+/// Word/byte order of multi-byte values (A = most significant byte)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ByteOrder {
+    /// Big-endian (Modbus standard)
+    #[default]
+    Abcd,
+    /// Word swap
+    Cdab,
+    /// Byte swap within each word
+    Badc,
+    /// Little-endian
+    Dcba,
+}
+
 // ===================== CONFIG STRUCTS =====================
 
 /// Modbus RTU configuration
@@ -117,6 +182,11 @@ pub struct ModbusRtuConfig {
     pub slave_id: u8,
     #[serde(default = "default_response_timeout")]
     pub response_timeout_ms: u32,
+    #[serde(default = "default_flow_control")]
+    pub flow_control: String, // "none", "rts_cts", "xon_xoff"
+    /// Serial framing: "rtu" (default) or "ascii"
+    #[serde(default = "default_serial_mode")]
+    pub mode: ModbusMode,
 }
 
 /// Modbus TCP configuration
@@ -127,16 +197,30 @@ pub struct ModbusTcpConfig {
     pub unit_id: u8,
     #[serde(default = "default_response_timeout")]
     pub response_timeout_ms: u32,
+    /// Raw RTU frames (with CRC) over the socket instead of MBAP
+    #[serde(default)]
+    pub rtu_over_tcp: bool,
 }
 
 fn default_response_timeout() -> u32 {
     1000
 }
 
+pub fn default_serial_mode() -> ModbusMode {
+    ModbusMode::Rtu
+}
+
+pub fn default_flow_control() -> String {
+    "none".to_string()
+}
+
 /// Modbus request parameters
-#[derive(Debug, Deserialize, Clone, Serialize)]
+#[derive(Debug, Deserialize, Clone, Serialize, Default)]
 pub struct ModbusRequest {
     pub connection_id: String,
+    /// Overrides the connection's slave ID (RTU) / unit ID (TCP) for this request
+    #[serde(default)]
+    pub slave_id: Option<u8>,
     pub function_code: u8,
     pub start_address: u16,
     #[serde(default)]
@@ -145,6 +229,110 @@ pub struct ModbusRequest {
     pub values: Option<Vec<u16>>,
     #[serde(default)]
     pub coil_values: Option<Vec<bool>>,
+    /// FC23: write start address (start_address/quantity describe the read)
+    #[serde(default)]
+    pub write_address: Option<u16>,
+    /// FC22: AND mask
+    #[serde(default)]
+    pub and_mask: Option<u16>,
+    /// FC22: OR mask
+    #[serde(default)]
+    pub or_mask: Option<u16>,
+    /// FC08: diagnostics sub-function (data words go in `values`)
+    #[serde(default)]
+    pub sub_function: Option<u16>,
+    /// FC43/14: read device ID code (1 = basic, 2 = regular, 3 = extended, 4 = single object)
+    #[serde(default)]
+    pub read_device_id_code: Option<u8>,
+    /// FC43/14: first object ID to read
+    #[serde(default)]
+    pub object_id: Option<u8>,
+    /// Typed decoding of the returned registers
+    #[serde(default)]
+    pub decode: Option<Vec<DecodeField>>,
+    /// Typed values to write (FC06/FC16/FC23), encoded in order from the write address.
+    /// Takes precedence over `values`.
+    #[serde(default)]
+    pub typed_values: Option<Vec<TypedValue>>,
+}
+
+/// One typed value to encode into registers
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TypedValue {
+    pub data_type: RegisterDataType,
+    /// Number, or text for string
+    pub value: serde_json::Value,
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+    /// Register count for string / BCD (string is padded with 0x00)
+    #[serde(default)]
+    pub length: u16,
+    /// raw = (value - offset) / scale
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub offset: Option<f64>,
+}
+
+/// One typed value to decode from a register response
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DecodeField {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Register address of the first word
+    pub address: u16,
+    pub data_type: RegisterDataType,
+    #[serde(default)]
+    pub byte_order: ByteOrder,
+    /// Register count for string / BCD
+    #[serde(default)]
+    pub length: u16,
+    /// value = raw * scale + offset
+    #[serde(default)]
+    pub scale: Option<f64>,
+    #[serde(default)]
+    pub offset: Option<f64>,
+}
+
+/// Decoded value
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DecodedValue {
+    pub name: Option<String>,
+    pub address: u16,
+    pub data_type: RegisterDataType,
+    /// Number or string, null if decoding failed
+    pub value: serde_json::Value,
+    pub error: Option<String>,
+}
+
+/// One object returned by Read Device Identification
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeviceIdObject {
+    pub id: u8,
+    #[serde(default)]
+    pub name: String,
+    pub value: String,
+}
+
+/// Read Device Identification (FC43/14) result
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct DeviceIdentification {
+    pub read_device_id_code: u8,
+    pub conformity_level: u8,
+    pub more_follows: bool,
+    pub next_object_id: u8,
+    pub objects: Vec<DeviceIdObject>,
+}
+
+/// Report Server ID (FC17) result
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct ServerIdReport {
+    /// First byte of the device specific data
+    pub server_id: u8,
+    /// Run indicator (0xFF = ON)
+    pub run_indicator: bool,
+    /// Full device specific data
+    pub raw: Vec<u8>,
 }
 
 /// Modbus response
@@ -165,6 +353,9 @@ pub struct ModbusResponse {
     pub response_frame: Vec<u8>,
     pub response_time_ms: u64,
     pub timestamp: u64,
+    pub device_identification: Option<DeviceIdentification>,
+    pub server_id: Option<ServerIdReport>,
+    pub decoded: Option<Vec<DecodedValue>>,
 }
 
 /// Modbus poll configuration
@@ -177,9 +368,158 @@ pub struct ModbusPollConfig {
 
 #[derive(Debug, Deserialize, Clone, Serialize)]
 pub struct ModbusPollRequest {
+    /// Overrides the connection's slave ID / unit ID for this request
+    #[serde(default)]
+    pub slave_id: Option<u8>,
     pub function_code: u8,
     pub start_address: u16,
     pub quantity: u16,
+    #[serde(default)]
+    pub decode: Option<Vec<DecodeField>>,
+}
+
+/// Bus scan configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModbusScanConfig {
+    pub connection_id: String,
+    #[serde(default = "default_scan_start_id")]
+    pub start_id: u8,
+    #[serde(default = "default_scan_end_id")]
+    pub end_id: u8,
+    /// Probe request (e.g. FC03 @ 0, or FC43 device ID)
+    #[serde(default = "default_scan_function_code")]
+    pub function_code: u8,
+    #[serde(default)]
+    pub start_address: u16,
+    #[serde(default = "default_scan_quantity")]
+    pub quantity: u16,
+    /// Per-probe response timeout
+    #[serde(default = "default_scan_timeout")]
+    pub timeout_ms: u32,
+    /// RTU only: baud rates to sweep (empty = keep current)
+    #[serde(default)]
+    pub baud_rates: Vec<u32>,
+    /// RTU only: parities to sweep (empty = keep current)
+    #[serde(default)]
+    pub parities: Vec<String>,
+}
+
+fn default_scan_start_id() -> u8 {
+    1
+}
+
+fn default_scan_end_id() -> u8 {
+    247
+}
+
+fn default_scan_function_code() -> u8 {
+    0x03
+}
+
+fn default_scan_quantity() -> u16 {
+    1
+}
+
+fn default_scan_timeout() -> u32 {
+    200
+}
+
+/// A slave that answered the scan probe
+#[derive(Debug, Serialize, Clone)]
+pub struct ModbusScanResult {
+    pub slave_id: u8,
+    pub baud_rate: Option<u32>,
+    pub parity: Option<String>,
+    /// Set when the answer was an exception response
+    pub exception_code: Option<u8>,
+    pub response_time_ms: u64,
+    pub device_identification: Option<DeviceIdentification>,
+}
+
+/// Whether a scan reply comes from a device at the probed ID. Normal and exception replies
+/// do, but gateway exceptions (0x0A path unavailable, 0x0B target failed to respond) are
+/// the gateway reporting that nothing answered behind it.
+pub fn is_device_reply(exception_code: Option<u8>) -> bool {
+    !matches!(exception_code, Some(0x0A) | Some(0x0B))
+}
+
+/// Scan progress event
+#[derive(Debug, Serialize, Clone)]
+pub struct ModbusScanProgress {
+    pub connection_id: String,
+    pub slave_id: u8,
+    pub baud_rate: Option<u32>,
+    pub parity: Option<String>,
+    pub scanned: usize,
+    pub total: usize,
+    pub found: Option<ModbusScanResult>,
+}
+
+/// Register map scan configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModbusMapScanConfig {
+    pub connection_id: String,
+    #[serde(default)]
+    pub slave_id: Option<u8>,
+    /// "coil", "discrete_input", "holding_register", "input_register" (empty = all)
+    #[serde(default)]
+    pub tables: Vec<String>,
+    #[serde(default)]
+    pub start_address: u16,
+    #[serde(default = "default_map_scan_end_address")]
+    pub end_address: u16,
+    /// Largest block read in one request (max 125)
+    #[serde(default = "default_map_scan_block")]
+    pub max_block: u16,
+    /// Largest address step while crossing a gap (1 = probe every address;
+    /// larger is faster but may miss ranges shorter than the step)
+    #[serde(default = "default_map_scan_gap_step")]
+    pub max_gap_step: u16,
+    #[serde(default)]
+    pub timeout_ms: Option<u32>,
+}
+
+fn default_map_scan_end_address() -> u16 {
+    9999
+}
+
+fn default_map_scan_block() -> u16 {
+    125
+}
+
+fn default_map_scan_gap_step() -> u16 {
+    1
+}
+
+/// A populated address range found by the map scanner
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct RegisterRange {
+    pub table: String,
+    pub start_address: u16,
+    pub end_address: u16,
+    pub count: u32,
+}
+
+/// Map scan progress event
+#[derive(Debug, Serialize, Clone)]
+pub struct ModbusMapScanProgress {
+    pub connection_id: String,
+    pub table: String,
+    pub address: u16,
+    pub probes: u32,
+    pub ranges_found: usize,
+}
+
+/// Result of one block probe
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeOutcome {
+    Ok,
+    /// Exception 0x02 or other failure: some address in the block is not readable
+    Failed,
+    /// Exception 0x01: the table is not implemented
+    IllegalFunction,
+    /// Scan was stopped
+    Stopped,
 }
 
 /// Connection status event
@@ -214,6 +554,11 @@ pub fn verify_crc16(frame: &[u8]) -> bool {
     calculated == received
 }
 
+/// Calculate LRC (Modbus ASCII): two's complement of the byte sum
+pub fn calculate_lrc(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |acc, &b| acc.wrapping_add(b)).wrapping_neg()
+}
+
 // ===================== FRAME BUILDING =====================
 
 /// Build Modbus RTU request frame
@@ -227,6 +572,23 @@ pub fn build_rtu_frame(slave_id: u8, function_code: u8, data: &[u8]) -> Vec<u8>
     frame
 }
 
+/// Build Modbus ASCII frame: ':' + hex(slave, fc, data, LRC) + CRLF
+pub fn build_ascii_frame(slave_id: u8, function_code: u8, data: &[u8]) -> Vec<u8> {
+    let mut binary = Vec::with_capacity(data.len() + 3);
+    binary.push(slave_id);
+    binary.push(function_code);
+    binary.extend_from_slice(data);
+    binary.push(calculate_lrc(&binary));
+
+    let mut frame = Vec::with_capacity(binary.len() * 2 + 3);
+    frame.push(b':');
+    for b in binary {
+        frame.extend_from_slice(format!("{:02X}", b).as_bytes());
+    }
+    frame.extend_from_slice(b"\r\n");
+    frame
+}
+
 /// Build Modbus TCP frame with MBAP header
 pub fn build_tcp_frame(
     transaction_id: u16,
@@ -311,6 +673,65 @@ pub fn build_write_multiple_coils_data(start_address: u16, values: &[bool]) -> V
     data
 }
 
+/// Build mask write register data (FC22)
+pub fn build_mask_write_register_data(address: u16, and_mask: u16, or_mask: u16) -> Vec<u8> {
+    let mut data = Vec::with_capacity(6);
+    data.extend_from_slice(&address.to_be_bytes());
+    data.extend_from_slice(&and_mask.to_be_bytes());
+    data.extend_from_slice(&or_mask.to_be_bytes());
+    data
+}
+
+/// Build read/write multiple registers data (FC23)
+pub fn build_read_write_multiple_registers_data(
+    read_address: u16,
+    read_quantity: u16,
+    write_address: u16,
+    values: &[u16],
+) -> Vec<u8> {
+    let mut data = build_read_request_data(read_address, read_quantity);
+    data.extend_from_slice(&build_write_multiple_registers_data(write_address, values));
+    data
+}
+
+/// Build diagnostics data (FC08)
+pub fn build_diagnostics_data(sub_function: u16, values: &[u16]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(2 + values.len() * 2);
+    data.extend_from_slice(&sub_function.to_be_bytes());
+    for value in values {
+        data.extend_from_slice(&value.to_be_bytes());
+    }
+    data
+}
+
+/// Build read device identification data (FC43, MEI type 14)
+pub fn build_read_device_identification_data(read_device_id_code: u8, object_id: u8) -> Vec<u8> {
+    vec![0x0E, read_device_id_code, object_id]
+}
+
+/// Standard object name for a device identification object ID
+pub fn device_id_object_name(id: u8) -> String {
+    match id {
+        0x00 => "VendorName".to_string(),
+        0x01 => "ProductCode".to_string(),
+        0x02 => "MajorMinorRevision".to_string(),
+        0x03 => "VendorUrl".to_string(),
+        0x04 => "ProductName".to_string(),
+        0x05 => "ModelName".to_string(),
+        0x06 => "UserApplicationName".to_string(),
+        0x80..=0xFF => format!("Private 0x{:02X}", id),
+        _ => format!("Reserved 0x{:02X}", id),
+    }
+}
+
+/// Register values to write: encoded typed values, or the raw `values`
+fn request_register_values(request: &ModbusRequest) -> Result<Option<Vec<u16>>, String> {
+    match &request.typed_values {
+        Some(typed) => encode_typed_values(typed).map(Some),
+        None => Ok(request.values.clone()),
+    }
+}
+
 /// Build request data based on function code
 pub fn build_request_data(request: &ModbusRequest) -> Result<Vec<u8>, String> {
     let fc = FunctionCode::from_u8(request.function_code)
@@ -336,8 +757,16 @@ pub fn build_request_data(request: &ModbusRequest) -> Result<Vec<u8>, String> {
             Ok(build_write_single_coil_data(request.start_address, value))
         }
         FunctionCode::WriteSingleRegister => {
-            let value = request
-                .values
+            let values = request_register_values(request)?;
+            if let (Some(_), Some(values)) = (&request.typed_values, &values) {
+                if values.len() > 1 {
+                    return Err(format!(
+                        "Typed value needs {} registers: use FC16 (Write Multiple Registers)",
+                        values.len()
+                    ));
+                }
+            }
+            let value = values
                 .as_ref()
                 .and_then(|v| v.first())
                 .copied()
@@ -355,14 +784,46 @@ pub fn build_request_data(request: &ModbusRequest) -> Result<Vec<u8>, String> {
             Ok(build_write_multiple_coils_data(request.start_address, values))
         }
         FunctionCode::WriteMultipleRegisters => {
-            let values = request
-                .values
-                .as_ref()
+            let values = request_register_values(request)?
                 .ok_or("Register values required for FC16")?;
             if values.is_empty() || values.len() > 123 {
                 return Err("Number of registers must be between 1 and 123".to_string());
             }
-            Ok(build_write_multiple_registers_data(request.start_address, values))
+            Ok(build_write_multiple_registers_data(request.start_address, &values))
+        }
+        FunctionCode::ReadExceptionStatus | FunctionCode::ReportServerId => Ok(Vec::new()),
+        FunctionCode::Diagnostics => {
+            let values = request.values.as_deref().unwrap_or(&[0x0000]);
+            Ok(build_diagnostics_data(request.sub_function.unwrap_or(0x0000), values))
+        }
+        FunctionCode::MaskWriteRegister => {
+            let and_mask = request.and_mask.ok_or("AND mask required for FC22")?;
+            let or_mask = request.or_mask.ok_or("OR mask required for FC22")?;
+            Ok(build_mask_write_register_data(request.start_address, and_mask, or_mask))
+        }
+        FunctionCode::ReadWriteMultipleRegisters => {
+            if request.quantity == 0 || request.quantity > 125 {
+                return Err("Read quantity must be between 1 and 125".to_string());
+            }
+            let write_address = request.write_address.ok_or("Write address required for FC23")?;
+            let values = request_register_values(request)?
+                .ok_or("Register values required for FC23")?;
+            if values.is_empty() || values.len() > 121 {
+                return Err("Number of registers to write must be between 1 and 121".to_string());
+            }
+            Ok(build_read_write_multiple_registers_data(
+                request.start_address,
+                request.quantity,
+                write_address,
+                &values,
+            ))
+        }
+        FunctionCode::ReadDeviceIdentification => {
+            let code = request.read_device_id_code.unwrap_or(0x01);
+            if !(0x01..=0x04).contains(&code) {
+                return Err("Read device ID code must be between 1 and 4".to_string());
+            }
+            Ok(build_read_device_identification_data(code, request.object_id.unwrap_or(0x00)))
         }
     }
 }
@@ -377,6 +838,8 @@ pub struct ParsedResponse {
     pub coils: Option<Vec<bool>>,
     pub is_exception: bool,
     pub exception_code: Option<u8>,
+    pub device_identification: Option<DeviceIdentification>,
+    pub server_id: Option<ServerIdReport>,
 }
 
 /// Parse Modbus RTU response frame
@@ -402,6 +865,8 @@ pub fn parse_rtu_response(frame: &[u8], expected_fc: u8) -> Result<ParsedRespons
             coils: None,
             is_exception: true,
             exception_code: Some(exception_code),
+            device_identification: None,
+            server_id: None,
         });
     }
 
@@ -417,6 +882,179 @@ pub fn parse_rtu_response(frame: &[u8], expected_fc: u8) -> Result<ParsedRespons
     parse_response_data(fc, data_frame, slave_id)
 }
 
+/// Decode a Modbus ASCII frame, check its LRC and return the equivalent RTU frame
+/// (with CRC) so the RTU parsers can be reused
+pub fn ascii_to_rtu_frame(frame: &[u8]) -> Result<Vec<u8>, String> {
+    let text = std::str::from_utf8(frame).map_err(|_| "Invalid ASCII frame".to_string())?;
+    let hex = text
+        .trim_end_matches(['\r', '\n'])
+        .strip_prefix(':')
+        .ok_or("Missing ':' start character")?;
+
+    if hex.len() < 6 || hex.len() % 2 != 0 {
+        return Err("Invalid ASCII frame length".to_string());
+    }
+
+    let binary = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| "Invalid hex in ASCII frame".to_string())?;
+
+    let (body, lrc) = binary.split_at(binary.len() - 1);
+    if calculate_lrc(body) != lrc[0] {
+        return Err("LRC error".to_string());
+    }
+
+    Ok(build_rtu_frame(body[0], body[1], &body[2..]))
+}
+
+/// Parse Modbus ASCII response frame
+pub fn parse_ascii_response(frame: &[u8], expected_fc: u8) -> Result<ParsedResponse, String> {
+    parse_rtu_response(&ascii_to_rtu_frame(frame)?, expected_fc)
+}
+
+/// Take one complete ASCII frame (':' .. CRLF) from a stream buffer.
+/// Bytes before the ':' start character are dropped. A new ':' before the CRLF, or
+/// `MAX_ASCII_FRAME_LEN` bytes without one, resynchronises on the next ':'.
+pub fn take_ascii_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    loop {
+        let Some(start) = buffer.iter().position(|&b| b == b':') else {
+            buffer.clear();
+            return None;
+        };
+        buffer.drain(..start);
+
+        let end = buffer.windows(2).position(|w| w == b"\r\n");
+        let search_end = end.unwrap_or(buffer.len()).min(MAX_ASCII_FRAME_LEN);
+        let restart = buffer[1..search_end].iter().position(|&b| b == b':');
+
+        match (end, restart) {
+            (_, Some(next)) => {
+                buffer.drain(..next + 1);
+            }
+            (Some(end), None) if end + 2 <= MAX_ASCII_FRAME_LEN => {
+                return Some(buffer.drain(..end + 2).collect());
+            }
+            (None, None) if buffer.len() <= MAX_ASCII_FRAME_LEN => return None,
+            // Longer than any valid frame and no ':' inside: drop it
+            _ => {
+                buffer.drain(..search_end);
+            }
+        }
+    }
+}
+
+/// Expected lengths of an RTU request or response starting at the front of `buffer`
+/// (function code layout, byte count when present). Empty for variable-length FCs.
+fn rtu_frame_lengths(buffer: &[u8]) -> Vec<usize> {
+    let byte_at = |idx: usize| buffer.get(idx).map(|&b| b as usize);
+    let fc = buffer[1];
+    if fc & 0x80 != 0 {
+        return vec![5];
+    }
+
+    let mut lengths: Vec<usize> = match fc {
+        0x01..=0x04 => [Some(8), byte_at(2).map(|n| 5 + n)].into_iter().flatten().collect(),
+        0x05 | 0x06 | 0x08 => vec![8],
+        0x07 => vec![4, 5],
+        0x0F | 0x10 => [Some(8), byte_at(6).map(|n| 9 + n)].into_iter().flatten().collect(),
+        0x11 => [Some(4), byte_at(2).map(|n| 5 + n)].into_iter().flatten().collect(),
+        0x16 => vec![10],
+        0x17 => [byte_at(2).map(|n| 5 + n), byte_at(10).map(|n| 13 + n)].into_iter().flatten().collect(),
+        _ => Vec::new(),
+    };
+    lengths.sort_unstable();
+    lengths
+}
+
+/// Reply length of an RTU read request, fixed by the requested quantity
+/// (None for other function codes)
+pub fn rtu_read_reply_length(request: &[u8]) -> Option<usize> {
+    let fc = *request.get(1)?;
+    match FunctionCode::from_u8(fc)? {
+        FunctionCode::ReadCoils
+        | FunctionCode::ReadDiscreteInputs
+        | FunctionCode::ReadHoldingRegisters
+        | FunctionCode::ReadInputRegisters
+        | FunctionCode::ReadWriteMultipleRegisters => {
+            let quantity = u16::from_be_bytes([*request.get(4)?, *request.get(5)?]);
+            Some(calculate_expected_response_length(fc, quantity, ModbusMode::Rtu))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a complete RTU frame is the reply to `request`: same slave, same function code
+/// (or its exception) and, for reads, the length fixed by the requested quantity
+pub fn is_rtu_reply_to(request: &[u8], frame: &[u8]) -> bool {
+    if request.len() < 2 || frame.len() < 5 || frame[0] != request[0] {
+        return false;
+    }
+    if frame[1] == request[1] | 0x80 {
+        return frame.len() == 5;
+    }
+    frame[1] == request[1] && rtu_read_reply_length(request).is_none_or(|len| frame.len() == len)
+}
+
+/// Take one RTU frame from a stream without MBAP (RTU over TCP).
+/// The lengths allowed by the function code are tried first; otherwise the shortest prefix
+/// whose CRC checks is taken, so frames arriving back to back are split. A buffer that
+/// grows past the largest ADU without a valid frame is dropped.
+pub fn take_rtu_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    if buffer.len() < 4 {
+        return None;
+    }
+
+    let lengths = rtu_frame_lengths(buffer);
+    if let Some(&len) = lengths
+        .iter()
+        .find(|&&len| buffer.len() >= len && verify_crc16(&buffer[..len]))
+    {
+        return Some(buffer.drain(..len).collect());
+    }
+    // Known layout not complete yet
+    if lengths.last().is_some_and(|&max| buffer.len() < max) {
+        return None;
+    }
+
+    if let Some(len) = (4..=buffer.len().min(256)).find(|&len| verify_crc16(&buffer[..len])) {
+        return Some(buffer.drain(..len).collect());
+    }
+    if buffer.len() > 256 {
+        buffer.clear();
+    }
+    None
+}
+
+/// Take one complete MBAP frame (header + PDU) from the front of a TCP stream buffer.
+/// Returns None until enough bytes have arrived. A header with a bad protocol ID or
+/// length cannot be resynchronised, so the buffer is cleared.
+pub fn take_mbap_frame(buffer: &mut Vec<u8>) -> Option<Vec<u8>> {
+    if buffer.len() < 6 {
+        return None;
+    }
+
+    let protocol_id = u16::from_be_bytes([buffer[2], buffer[3]]);
+    let length = u16::from_be_bytes([buffer[4], buffer[5]]) as usize;
+    if protocol_id != 0 || !(2..=MAX_MBAP_LENGTH).contains(&length) {
+        buffer.clear();
+        return None;
+    }
+
+    let frame_len = 6 + length;
+    if buffer.len() < frame_len {
+        return None;
+    }
+
+    Some(buffer.drain(..frame_len).collect())
+}
+
+/// Transaction ID of an MBAP frame
+pub fn mbap_transaction_id(frame: &[u8]) -> Option<u16> {
+    (frame.len() >= 2).then(|| u16::from_be_bytes([frame[0], frame[1]]))
+}
+
 /// Parse Modbus TCP response frame
 pub fn parse_tcp_response(frame: &[u8], expected_fc: u8) -> Result<ParsedResponse, String> {
     if frame.len() < 9 {
@@ -440,6 +1078,8 @@ pub fn parse_tcp_response(frame: &[u8], expected_fc: u8) -> Result<ParsedRespons
             coils: None,
             is_exception: true,
             exception_code: Some(exception_code),
+            device_identification: None,
+            server_id: None,
         });
     }
 
@@ -483,9 +1123,13 @@ fn parse_response_data(fc: u8, data_frame: &[u8], slave_id: u8) -> Result<Parsed
                 coils: Some(coils),
                 is_exception: false,
                 exception_code: None,
+                device_identification: None,
+                server_id: None,
             })
         }
-        Some(FunctionCode::ReadHoldingRegisters) | Some(FunctionCode::ReadInputRegisters) => {
+        Some(FunctionCode::ReadHoldingRegisters)
+        | Some(FunctionCode::ReadInputRegisters)
+        | Some(FunctionCode::ReadWriteMultipleRegisters) => {
             if data_frame.is_empty() {
                 return Err("Empty response data".to_string());
             }
@@ -508,12 +1152,15 @@ fn parse_response_data(fc: u8, data_frame: &[u8], slave_id: u8) -> Result<Parsed
                 coils: None,
                 is_exception: false,
                 exception_code: None,
+                device_identification: None,
+                server_id: None,
             })
         }
         Some(FunctionCode::WriteSingleCoil)
         | Some(FunctionCode::WriteSingleRegister)
         | Some(FunctionCode::WriteMultipleCoils)
-        | Some(FunctionCode::WriteMultipleRegisters) => {
+        | Some(FunctionCode::WriteMultipleRegisters)
+        | Some(FunctionCode::MaskWriteRegister) => {
             // Write responses echo the address and value/quantity
             Ok(ParsedResponse {
                 slave_id,
@@ -522,12 +1169,119 @@ fn parse_response_data(fc: u8, data_frame: &[u8], slave_id: u8) -> Result<Parsed
                 coils: None,
                 is_exception: false,
                 exception_code: None,
+                device_identification: None,
+                server_id: None,
+            })
+        }
+        Some(FunctionCode::ReadExceptionStatus) => {
+            let status = *data_frame.first().ok_or("Empty response data")?;
+            Ok(ParsedResponse {
+                slave_id,
+                function_code: fc,
+                data: Some(vec![status as u16]),
+                coils: None,
+                is_exception: false,
+                exception_code: None,
+                device_identification: None,
+                server_id: None,
+            })
+        }
+        Some(FunctionCode::Diagnostics) => {
+            if data_frame.len() < 2 {
+                return Err("Incomplete diagnostics data".to_string());
+            }
+            // data = [sub-function, data words...]
+            let words = data_frame
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            Ok(ParsedResponse {
+                slave_id,
+                function_code: fc,
+                data: Some(words),
+                coils: None,
+                is_exception: false,
+                exception_code: None,
+                device_identification: None,
+                server_id: None,
+            })
+        }
+        Some(FunctionCode::ReportServerId) => {
+            let byte_count = *data_frame.first().ok_or("Empty response data")? as usize;
+            if byte_count == 0 || data_frame.len() < 1 + byte_count {
+                return Err("Incomplete server ID data".to_string());
+            }
+            let raw = data_frame[1..1 + byte_count].to_vec();
+            Ok(ParsedResponse {
+                slave_id,
+                function_code: fc,
+                data: None,
+                coils: None,
+                is_exception: false,
+                exception_code: None,
+                device_identification: None,
+                server_id: Some(ServerIdReport {
+                    server_id: raw[0],
+                    run_indicator: raw.get(1) == Some(&0xFF),
+                    raw,
+                }),
+            })
+        }
+        Some(FunctionCode::ReadDeviceIdentification) => {
+            let identification = parse_device_identification(data_frame)?;
+            Ok(ParsedResponse {
+                slave_id,
+                function_code: fc,
+                data: None,
+                coils: None,
+                is_exception: false,
+                exception_code: None,
+                device_identification: Some(identification),
+                server_id: None,
             })
         }
         None => Err(format!("Unknown function code: {}", fc)),
     }
 }
 
+/// Parse Read Device Identification response data (after FC)
+pub fn parse_device_identification(data: &[u8]) -> Result<DeviceIdentification, String> {
+    if data.len() < 6 {
+        return Err("Incomplete device identification data".to_string());
+    }
+    if data[0] != 0x0E {
+        return Err(format!("Unsupported MEI type: 0x{:02X}", data[0]));
+    }
+
+    let object_count = data[5] as usize;
+    let mut objects = Vec::with_capacity(object_count);
+    let mut pos = 6;
+    for _ in 0..object_count {
+        if pos + 2 > data.len() {
+            return Err("Incomplete device identification object".to_string());
+        }
+        let id = data[pos];
+        let len = data[pos + 1] as usize;
+        let value = data
+            .get(pos + 2..pos + 2 + len)
+            .ok_or("Incomplete device identification object")?;
+        objects.push(DeviceIdObject {
+            id,
+            name: device_id_object_name(id),
+            value: String::from_utf8_lossy(value).to_string(),
+        });
+        pos += 2 + len;
+    }
+
+    Ok(DeviceIdentification {
+        read_device_id_code: data[1],
+        conformity_level: data[2],
+        more_follows: data[3] == 0xFF,
+        next_object_id: data[4],
+        objects,
+    })
+}
+
 /// Format exception code to human-readable message
 pub fn format_exception_error(code: u8) -> String {
     match code {
@@ -544,6 +1298,319 @@ pub fn format_exception_error(code: u8) -> String {
     }
 }
 
+// ===================== TYPED DECODING =====================
+
+/// Reorder big-endian register bytes into ABCD (most significant first) order
+pub fn reorder_register_bytes(registers: &[u16], order: ByteOrder) -> Vec<u8> {
+    let swap_words = matches!(order, ByteOrder::Cdab | ByteOrder::Dcba);
+    let swap_bytes = matches!(order, ByteOrder::Badc | ByteOrder::Dcba);
+
+    let mut words: Vec<u16> = registers.to_vec();
+    if swap_words {
+        words.reverse();
+    }
+    words
+        .iter()
+        .flat_map(|w| if swap_bytes { w.to_le_bytes() } else { w.to_be_bytes() })
+        .collect()
+}
+
+fn decode_bcd(bytes: &[u8]) -> Result<u64, String> {
+    bytes.iter().try_fold(0u64, |acc, &b| {
+        let (hi, lo) = (b >> 4, b & 0x0F);
+        if hi > 9 || lo > 9 {
+            return Err(format!("Invalid BCD byte 0x{:02X}", b));
+        }
+        acc.checked_mul(100)
+            .and_then(|v| v.checked_add((hi * 10 + lo) as u64))
+            .ok_or_else(|| "BCD value too large".to_string())
+    })
+}
+
+/// Decode one field from raw registers
+pub fn decode_field(registers: &[u16], field: &DecodeField) -> Result<serde_json::Value, String> {
+    if field.data_type == RegisterDataType::Bcd && field.length as usize > MAX_BCD_REGISTERS {
+        return Err(format!("BCD fields are limited to {} registers", MAX_BCD_REGISTERS));
+    }
+    let bytes = reorder_register_bytes(registers, field.byte_order);
+    let as_array = |n: usize| -> [u8; 8] {
+        let mut buf = [0u8; 8];
+        buf[8 - n..].copy_from_slice(&bytes[..n]);
+        buf
+    };
+
+    let raw: f64 = match field.data_type {
+        RegisterDataType::String => {
+            let text: String = String::from_utf8_lossy(&bytes)
+                .trim_end_matches(['\0', ' '])
+                .to_string();
+            return Ok(serde_json::Value::String(text));
+        }
+        RegisterDataType::U16 => u16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+        RegisterDataType::I16 => i16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+        RegisterDataType::U32 => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        RegisterDataType::I32 => i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        RegisterDataType::F32 => f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+        RegisterDataType::F64 => f64::from_be_bytes(as_array(8)),
+        RegisterDataType::U64 => {
+            let value = u64::from_be_bytes(as_array(8));
+            if field.scale.is_none() && field.offset.is_none() {
+                return Ok(serde_json::Value::from(value));
+            }
+            value as f64
+        }
+        RegisterDataType::Bcd => decode_bcd(&bytes)? as f64,
+    };
+
+    if field.scale.is_none() && field.offset.is_none() {
+        let is_integer = !matches!(field.data_type, RegisterDataType::F32 | RegisterDataType::F64);
+        if is_integer {
+            return Ok(serde_json::Value::from(raw as i64));
+        }
+    }
+
+    let value = raw * field.scale.unwrap_or(1.0) + field.offset.unwrap_or(0.0);
+    serde_json::Number::from_f64(value)
+        .map(serde_json::Value::Number)
+        .ok_or_else(|| format!("Value is not finite: {}", value))
+}
+
+/// Decode typed fields from a register block starting at `start_address`
+pub fn decode_registers(start_address: u16, registers: &[u16], fields: &[DecodeField]) -> Vec<DecodedValue> {
+    fields
+        .iter()
+        .map(|field| {
+            let count = field.data_type.register_count(field.length);
+            let begin = field.address.wrapping_sub(start_address) as usize;
+            let result = if field.address < start_address || begin + count > registers.len() {
+                Err("Address outside of response".to_string())
+            } else {
+                decode_field(&registers[begin..begin + count], field)
+            };
+
+            let (value, error) = match result {
+                Ok(value) => (value, None),
+                Err(e) => (serde_json::Value::Null, Some(e)),
+            };
+            DecodedValue {
+                name: field.name.clone(),
+                address: field.address,
+                data_type: field.data_type,
+                value,
+                error,
+            }
+        })
+        .collect()
+}
+
+fn encode_bcd(value: u64, byte_len: usize) -> Result<Vec<u8>, String> {
+    let digits = format!("{:0width$}", value, width = byte_len * 2);
+    if digits.len() > byte_len * 2 {
+        return Err(format!("Value {} does not fit in {} BCD digits", value, byte_len * 2));
+    }
+    Ok(digits
+        .as_bytes()
+        .chunks(2)
+        .map(|d| ((d[0] - b'0') << 4) | (d[1] - b'0'))
+        .collect())
+}
+
+/// Encode one typed value into register words
+pub fn encode_typed_value(typed: &TypedValue) -> Result<Vec<u16>, String> {
+    let count = typed.data_type.register_count(typed.length);
+
+    let bytes: Vec<u8> = if typed.data_type == RegisterDataType::String {
+        let text = typed.value.as_str().ok_or("String value required")?.as_bytes();
+        let len = if typed.length == 0 { text.len().div_ceil(2) * 2 } else { count * 2 };
+        if text.len() > len {
+            return Err(format!("String longer than {} bytes", len));
+        }
+        let mut bytes = text.to_vec();
+        bytes.resize(len.max(2), 0);
+        bytes
+    } else {
+        let number = typed.value.as_f64().ok_or("Numeric value required")?;
+        if typed.scale == Some(0.0) {
+            return Err("Scale must not be 0".to_string());
+        }
+        let raw = (number - typed.offset.unwrap_or(0.0)) / typed.scale.unwrap_or(1.0);
+        let int = |min: f64, max: f64| -> Result<i128, String> {
+            let rounded = raw.round();
+            if !(min..=max).contains(&rounded) {
+                return Err(format!("Value {} out of range for {:?}", raw, typed.data_type));
+            }
+            Ok(rounded as i128)
+        };
+
+        match typed.data_type {
+            RegisterDataType::U16 => (int(0.0, u16::MAX as f64)? as u16).to_be_bytes().to_vec(),
+            RegisterDataType::I16 => (int(i16::MIN as f64, i16::MAX as f64)? as i16).to_be_bytes().to_vec(),
+            RegisterDataType::U32 => (int(0.0, u32::MAX as f64)? as u32).to_be_bytes().to_vec(),
+            RegisterDataType::I32 => (int(i32::MIN as f64, i32::MAX as f64)? as i32).to_be_bytes().to_vec(),
+            RegisterDataType::F32 => (raw as f32).to_be_bytes().to_vec(),
+            RegisterDataType::F64 => raw.to_be_bytes().to_vec(),
+            RegisterDataType::U64 => {
+                // Keep full precision for plain integers
+                let value = match typed.value.as_u64() {
+                    Some(v) if typed.scale.is_none() && typed.offset.is_none() => v,
+                    _ => int(0.0, u64::MAX as f64)? as u64,
+                };
+                value.to_be_bytes().to_vec()
+            }
+            RegisterDataType::Bcd => {
+                if count > MAX_BCD_REGISTERS {
+                    return Err(format!("BCD values are limited to {} registers", MAX_BCD_REGISTERS));
+                }
+                let max = 10u64.pow(count as u32 * 4) - 1;
+                let value = match typed.value.as_u64() {
+                    Some(v) if typed.scale.is_none() && typed.offset.is_none() => v,
+                    _ => int(0.0, max as f64)? as u64,
+                };
+                if value > max {
+                    return Err(format!("Value {} does not fit in {} BCD digits", value, count * 4));
+                }
+                encode_bcd(value, count * 2)?
+            }
+            RegisterDataType::String => unreachable!(),
+        }
+    };
+
+    let words: Vec<u16> = bytes
+        .chunks(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect();
+
+    // Byte reordering is its own inverse
+    Ok(reorder_register_bytes(&words, typed.byte_order)
+        .chunks(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect())
+}
+
+/// Encode typed values back to back into register words
+pub fn encode_typed_values(values: &[TypedValue]) -> Result<Vec<u16>, String> {
+    let mut registers = Vec::new();
+    for value in values {
+        registers.extend(encode_typed_value(value)?);
+    }
+    Ok(registers)
+}
+
+/// Attach decoded values to a response (no-op without register data or fields)
+pub fn apply_decode(response: &mut ModbusResponse, fields: Option<&Vec<DecodeField>>) {
+    if let (Some(fields), Some(data)) = (fields, response.data.as_ref()) {
+        response.decoded = Some(decode_registers(response.start_address, data, fields));
+    }
+}
+
+// ===================== REGISTER MAP SCAN =====================
+
+/// Function code used to read a table by name
+pub fn table_read_function_code(table: &str) -> Option<u8> {
+    match table {
+        "coil" => Some(0x01),
+        "discrete_input" => Some(0x02),
+        "holding_register" => Some(0x03),
+        "input_register" => Some(0x04),
+        _ => None,
+    }
+}
+
+/// Walk `start..=end` and return the merged populated ranges (start, end).
+///
+/// Reads go in blocks of up to `max_block`. When a block fails, the implemented prefix is
+/// found by binary search. Inside a gap, single addresses are probed with a step that
+/// doubles up to `max_gap_step` (1 = probe every address), and once an implemented address
+/// is hit the start of its range is found by binary search.
+/// `probe` gets (address, quantity, ranges found so far) so callers can report progress.
+pub fn scan_address_space(
+    start: u16,
+    end: u16,
+    max_block: u16,
+    max_gap_step: u16,
+    mut probe: impl FnMut(u16, u16, usize) -> ProbeOutcome,
+) -> Vec<(u16, u16)> {
+    let max_block = max_block.clamp(1, 125) as u32;
+    let max_gap_step = (max_gap_step as u32).clamp(1, max_block);
+    let end = end as u32;
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    let mut addr = start as u32;
+
+    let push_range = |ranges: &mut Vec<(u16, u16)>, addr: u32, qty: u32| {
+        let (first, last) = (addr as u16, (addr + qty - 1) as u16);
+        match ranges.last_mut() {
+            Some(prev) if prev.1 as u32 + 1 == first as u32 => prev.1 = last,
+            _ => ranges.push((first, last)),
+        }
+    };
+
+    // Ok(true) = readable, Ok(false) = not readable, Err = stop scanning
+    let mut readable = |ranges: &[(u16, u16)], addr: u32, qty: u32| match probe(addr as u16, qty as u16, ranges.len()) {
+        ProbeOutcome::Ok => Ok(true),
+        ProbeOutcome::Failed => Ok(false),
+        ProbeOutcome::IllegalFunction | ProbeOutcome::Stopped => Err(()),
+    };
+
+    while addr <= end {
+        let qty = max_block.min(end - addr + 1);
+        match readable(&ranges, addr, qty) {
+            Ok(true) => {
+                push_range(&mut ranges, addr, qty);
+                addr += qty;
+                continue;
+            }
+            Ok(false) => {}
+            Err(()) => break,
+        }
+
+        // Longest readable prefix of the block
+        let (mut lo, mut hi) = (0, qty - 1);
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            match readable(&ranges, addr, mid) {
+                Ok(true) => lo = mid,
+                Ok(false) => hi = mid - 1,
+                Err(()) => return ranges,
+            }
+        }
+        if lo > 0 {
+            push_range(&mut ranges, addr, lo);
+        }
+
+        // addr + lo is not implemented: step through the gap
+        let mut gap_end = addr + lo;
+        let mut step = 1;
+        let hit = loop {
+            if gap_end >= end {
+                return ranges;
+            }
+            let next = (gap_end + step).min(end);
+            match readable(&ranges, next, 1) {
+                Ok(true) => break next,
+                Ok(false) => {
+                    gap_end = next;
+                    step = (step * 2).min(max_gap_step);
+                }
+                Err(()) => return ranges,
+            }
+        };
+
+        // First readable address of the range containing `hit`
+        let (mut lo, mut hi) = (gap_end + 1, hit);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match readable(&ranges, mid, hit - mid + 1) {
+                Ok(true) => hi = mid,
+                Ok(false) => lo = mid + 1,
+                Err(()) => return ranges,
+            }
+        }
+        addr = lo;
+    }
+
+    ranges
+}
+
 // ===================== TIMING UTILITIES =====================
 
 /// Calculate RTU inter-frame delay (3.5 character times) in microseconds
@@ -561,6 +1628,7 @@ pub fn calculate_expected_response_length(fc: u8, quantity: u16, mode: ModbusMod
     let base_len = match mode {
         ModbusMode::Rtu => 5, // slave + fc + data + crc(2)
         ModbusMode::Tcp => 9, // MBAP(7) + fc + data
+        ModbusMode::Ascii => 5, // same as RTU once decoded (LRC in place of CRC)
     };
 
     match FunctionCode::from_u8(fc) {
@@ -568,7 +1636,9 @@ pub fn calculate_expected_response_length(fc: u8, quantity: u16, mode: ModbusMod
             let byte_count = ((quantity as usize) + 7) / 8;
             base_len + byte_count
         }
-        Some(FunctionCode::ReadHoldingRegisters) | Some(FunctionCode::ReadInputRegisters) => {
+        Some(FunctionCode::ReadHoldingRegisters)
+        | Some(FunctionCode::ReadInputRegisters)
+        | Some(FunctionCode::ReadWriteMultipleRegisters) => {
             base_len + (quantity as usize) * 2
         }
         Some(FunctionCode::WriteSingleCoil) | Some(FunctionCode::WriteSingleRegister) => {
@@ -577,6 +1647,12 @@ pub fn calculate_expected_response_length(fc: u8, quantity: u16, mode: ModbusMod
         Some(FunctionCode::WriteMultipleCoils) | Some(FunctionCode::WriteMultipleRegisters) => {
             base_len + 2 // Address + quantity
         }
+        Some(FunctionCode::MaskWriteRegister) => base_len + 5, // Address + AND + OR
+        Some(FunctionCode::ReadExceptionStatus) => base_len,
+        // Variable length: read until the frame is complete (max ADU 256 bytes)
+        Some(FunctionCode::Diagnostics)
+        | Some(FunctionCode::ReportServerId)
+        | Some(FunctionCode::ReadDeviceIdentification) => 256,
         None => base_len,
     }
 }
@@ -632,6 +1708,289 @@ mod tests {
         assert_eq!(frame[7], 0x03); // FC
     }
 
+    #[test]
+    fn test_parse_device_identification() {
+        // 01 2B 0E 01 01 00 00 02 | 00 03 "ACM" | 01 02 "D7"
+        let pdu = [
+            0x0E, 0x01, 0x01, 0x00, 0x00, 0x02, 0x00, 0x03, b'A', b'C', b'M', 0x01, 0x02, b'D', b'7',
+        ];
+        let frame = build_rtu_frame(1, 0x2B, &pdu);
+        let parsed = parse_rtu_response(&frame, 0x2B).unwrap();
+        let id = parsed.device_identification.unwrap();
+        assert!(!id.more_follows);
+        assert_eq!(id.objects.len(), 2);
+        assert_eq!(id.objects[0].name, "VendorName");
+        assert_eq!(id.objects[0].value, "ACM");
+        assert_eq!(id.objects[1].value, "D7");
+    }
+
+    #[test]
+    fn test_build_read_write_multiple_registers_data() {
+        let data = build_read_write_multiple_registers_data(0x0003, 6, 0x000E, &[0x00FF, 0x00FF, 0x00FF]);
+        assert_eq!(
+            data,
+            vec![0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF]
+        );
+    }
+
+    #[test]
+    fn test_decode_float_word_orders() {
+        // 123.456f32 = 0x42F6E979
+        let field = |order| DecodeField {
+            name: None,
+            address: 0,
+            data_type: RegisterDataType::F32,
+            byte_order: order,
+            length: 0,
+            scale: None,
+            offset: None,
+        };
+        let cases = [
+            (ByteOrder::Abcd, [0x42F6, 0xE979]),
+            (ByteOrder::Cdab, [0xE979, 0x42F6]),
+            (ByteOrder::Badc, [0xF642, 0x79E9]),
+            (ByteOrder::Dcba, [0x79E9, 0xF642]),
+        ];
+        for (order, regs) in cases {
+            let value = decode_field(&regs, &field(order)).unwrap().as_f64().unwrap();
+            assert!((value - 123.456).abs() < 1e-4, "{:?}", order);
+        }
+    }
+
+    #[test]
+    fn test_decode_registers_scale_bcd_string() {
+        let registers = [0xFF38, 0x1234, 0x4142, 0x4300];
+        let fields = vec![
+            DecodeField {
+                name: Some("temp".to_string()),
+                address: 10,
+                data_type: RegisterDataType::I16,
+                byte_order: ByteOrder::Abcd,
+                length: 0,
+                scale: Some(0.1),
+                offset: Some(1.0),
+            },
+            DecodeField {
+                name: None,
+                address: 11,
+                data_type: RegisterDataType::Bcd,
+                byte_order: ByteOrder::Abcd,
+                length: 1,
+                scale: None,
+                offset: None,
+            },
+            DecodeField {
+                name: None,
+                address: 12,
+                data_type: RegisterDataType::String,
+                byte_order: ByteOrder::Abcd,
+                length: 2,
+                scale: None,
+                offset: None,
+            },
+            DecodeField {
+                name: None,
+                address: 13,
+                data_type: RegisterDataType::U32,
+                byte_order: ByteOrder::Abcd,
+                length: 0,
+                scale: None,
+                offset: None,
+            },
+        ];
+        let decoded = decode_registers(10, &registers, &fields);
+        assert!((decoded[0].value.as_f64().unwrap() - -19.0).abs() < 1e-9);
+        assert_eq!(decoded[1].value, serde_json::json!(1234));
+        assert_eq!(decoded[2].value, serde_json::json!("ABC"));
+        assert!(decoded[3].error.is_some());
+    }
+
+    #[test]
+    fn test_decode_bcd_overflow() {
+        // 20 digits do not fit in u64: error instead of overflow
+        assert_eq!(decode_bcd(&[0x99; 10]), Err("BCD value too large".to_string()));
+        assert_eq!(decode_bcd(&[0x99; 8]), Ok(9_999_999_999_999_999));
+
+        let field = DecodeField {
+            name: None,
+            address: 0,
+            data_type: RegisterDataType::Bcd,
+            byte_order: ByteOrder::Abcd,
+            length: 5,
+            scale: None,
+            offset: None,
+        };
+        assert!(decode_field(&[0x1234; 5], &field).is_err());
+    }
+
+    #[test]
+    fn test_encode_typed_values_round_trip() {
+        let typed = |data_type, value, byte_order| TypedValue {
+            data_type,
+            value,
+            byte_order,
+            length: 0,
+            scale: None,
+            offset: None,
+        };
+        let request = ModbusRequest {
+            connection_id: "m".to_string(),
+            function_code: 0x10,
+            start_address: 100,
+            typed_values: Some(vec![
+                typed(RegisterDataType::F32, serde_json::json!(123.456), ByteOrder::Cdab),
+                typed(RegisterDataType::I16, serde_json::json!(-2), ByteOrder::Abcd),
+                typed(RegisterDataType::String, serde_json::json!("ABC"), ByteOrder::Abcd),
+            ]),
+            ..Default::default()
+        };
+
+        let data = build_request_data(&request).unwrap();
+        // addr, qty = 5, byte count = 10
+        assert_eq!(&data[..5], &[0x00, 0x64, 0x00, 0x05, 0x0A]);
+        assert_eq!(&data[5..], &[0xE9, 0x79, 0x42, 0xF6, 0xFF, 0xFE, 0x41, 0x42, 0x43, 0x00]);
+
+        let bcd = TypedValue { length: 1, ..typed(RegisterDataType::Bcd, serde_json::json!(1234), ByteOrder::Abcd) };
+        assert_eq!(encode_typed_value(&bcd).unwrap(), vec![0x1234]);
+        assert!(encode_typed_value(&typed(RegisterDataType::U16, serde_json::json!(70000), ByteOrder::Abcd)).is_err());
+
+        // BCD must fit the digits of its registers, scale 0 is rejected
+        assert!(encode_typed_value(&TypedValue { value: serde_json::json!(12345), ..bcd.clone() }).is_err());
+        assert!(encode_typed_value(&TypedValue { value: serde_json::json!(1e30), ..bcd.clone() }).is_err());
+        assert!(encode_typed_value(&TypedValue { scale: Some(0.0), ..bcd.clone() }).is_err());
+
+        // FC06 cannot carry a multi-register value
+        let request = ModbusRequest {
+            function_code: 0x06,
+            typed_values: Some(vec![typed(RegisterDataType::F32, serde_json::json!(1.5), ByteOrder::Abcd)]),
+            ..Default::default()
+        };
+        assert!(build_request_data(&request).unwrap_err().contains("FC16"));
+    }
+
+    #[test]
+    fn test_scan_address_space_finds_ranges() {
+        // Implemented: 0..=9 and 100..=119
+        let implemented = |a: u16| a <= 9 || (100..=119).contains(&a);
+        let mut probes = 0;
+        let mut reported = 0;
+        let ranges = scan_address_space(0, 299, 125, 16, |addr, qty, found| {
+            probes += 1;
+            reported = found;
+            if (addr..addr + qty).all(implemented) {
+                ProbeOutcome::Ok
+            } else {
+                ProbeOutcome::Failed
+            }
+        });
+        assert_eq!(ranges, vec![(0, 9), (100, 119)]);
+        assert!(probes < 100, "{} probes", probes);
+        // Ranges are reported while the table is still being scanned
+        assert_eq!(reported, 2);
+
+        let ranges = scan_address_space(0, 99, 125, 1, |_, _, _| ProbeOutcome::IllegalFunction);
+        assert!(ranges.is_empty());
+    }
+
+    #[test]
+    fn test_scan_skips_gateway_exceptions() {
+        assert!(is_device_reply(None));
+        // Illegal data address: the device exists
+        assert!(is_device_reply(Some(0x02)));
+        assert!(!is_device_reply(Some(0x0A)));
+        assert!(!is_device_reply(Some(0x0B)));
+    }
+
+    #[test]
+    fn test_ascii_frame_round_trip() {
+        // Read 10 holding registers from slave 1 at address 0
+        let frame = build_ascii_frame(0x01, 0x03, &[0x00, 0x00, 0x00, 0x0A]);
+        assert_eq!(frame, b":01030000000AF2\r\n".to_vec());
+
+        let rtu = ascii_to_rtu_frame(&frame).unwrap();
+        assert_eq!(rtu, vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]);
+        assert!(ascii_to_rtu_frame(b":01030000000AF3\r\n").is_err());
+
+        // Noise before ':' is dropped, frame split across reads
+        let mut buffer = b"\x00:0103020".to_vec();
+        assert_eq!(take_ascii_frame(&mut buffer), None);
+        buffer.extend_from_slice(b"02AD0\r\n");
+        let frame = take_ascii_frame(&mut buffer).unwrap();
+        let parsed = parse_ascii_response(&frame, 0x03).unwrap();
+        assert_eq!(parsed.data, Some(vec![0x002A]));
+        assert!(buffer.is_empty());
+
+        // Two frames in one read, then a truncated frame restarted by a new ':'
+        let mut buffer = b":01030000000AF2\r\n:01030000000AF2\r\n:0103:01030000000AF2\r\n".to_vec();
+        for _ in 0..3 {
+            assert_eq!(take_ascii_frame(&mut buffer).unwrap(), b":01030000000AF2\r\n".to_vec());
+        }
+        assert!(buffer.is_empty());
+
+        // No CRLF ever: the buffer stays bounded
+        let mut buffer = b":".to_vec();
+        buffer.extend(std::iter::repeat_n(b'0', 2000));
+        assert_eq!(take_ascii_frame(&mut buffer), None);
+        assert!(buffer.len() <= MAX_ASCII_FRAME_LEN);
+    }
+
+    #[test]
+    fn test_take_rtu_frame_splits_back_to_back_frames() {
+        let read = build_rtu_frame(0x01, 0x03, &[0x00, 0x00, 0x00, 0x0A]);
+        let write = build_rtu_frame(0x01, 0x10, &[0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02]);
+
+        // Two requests in one TCP segment, the second split across reads
+        let mut buffer = read.clone();
+        buffer.extend_from_slice(&write[..5]);
+        assert_eq!(take_rtu_frame(&mut buffer).unwrap(), read);
+        assert_eq!(take_rtu_frame(&mut buffer), None);
+        buffer.extend_from_slice(&write[5..]);
+        buffer.extend_from_slice(&read);
+        assert_eq!(take_rtu_frame(&mut buffer).unwrap(), write);
+        assert_eq!(take_rtu_frame(&mut buffer).unwrap(), read);
+        assert!(buffer.is_empty());
+
+        // Responses are framed by their byte count
+        let reply = build_rtu_frame(0x01, 0x03, &[0x04, 0x00, 0x2A, 0x00, 0x2B]);
+        let mut buffer = [reply.clone(), reply.clone()].concat();
+        assert_eq!(take_rtu_frame(&mut buffer).unwrap(), reply);
+        assert_eq!(take_rtu_frame(&mut buffer).unwrap(), reply);
+    }
+
+    #[test]
+    fn test_is_rtu_reply_to() {
+        let request = build_rtu_frame(0x01, 0x03, &[0x00, 0x00, 0x00, 0x02]);
+        assert!(is_rtu_reply_to(&request, &build_rtu_frame(0x01, 0x03, &[0x04, 0, 1, 0, 2])));
+        assert!(is_rtu_reply_to(&request, &build_rtu_frame(0x01, 0x83, &[0x02])));
+        // Late reply of an earlier request: other quantity, function code or slave
+        assert!(!is_rtu_reply_to(&request, &build_rtu_frame(0x01, 0x03, &[0x02, 0, 1])));
+        assert!(!is_rtu_reply_to(&request, &build_rtu_frame(0x01, 0x04, &[0x04, 0, 1, 0, 2])));
+        assert!(!is_rtu_reply_to(&request, &build_rtu_frame(0x02, 0x03, &[0x04, 0, 1, 0, 2])));
+    }
+
+    #[test]
+    fn test_take_mbap_frame_reassembles_stream() {
+        let first = build_tcp_frame(7, 1, 0x03, &[0x02, 0x00, 0x2A]);
+        let second = build_tcp_frame(8, 1, 0x06, &[0x00, 0x01, 0x00, 0x05]);
+
+        // First frame split across two reads, second frame glued to its tail
+        let mut buffer = first[..4].to_vec();
+        assert_eq!(take_mbap_frame(&mut buffer), None);
+        buffer.extend_from_slice(&first[4..]);
+        buffer.extend_from_slice(&second);
+
+        let frame = take_mbap_frame(&mut buffer).unwrap();
+        assert_eq!(frame, first);
+        assert_eq!(mbap_transaction_id(&frame), Some(7));
+        assert_eq!(take_mbap_frame(&mut buffer).unwrap(), second);
+        assert!(buffer.is_empty());
+
+        // Bad protocol ID drops the buffer
+        let mut buffer = vec![0x00, 0x01, 0x12, 0x34, 0x00, 0x03, 0x01, 0x03, 0x00];
+        assert_eq!(take_mbap_frame(&mut buffer), None);
+        assert!(buffer.is_empty());
+    }
+
     #[test]
     fn test_verify_crc() {
         let frame = [0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD];
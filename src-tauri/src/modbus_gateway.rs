@@ -0,0 +1,250 @@
+// Modbus Gateway Module for TermiPro
+// Modbus TCP to RTU gateway: MBAP requests from TCP masters are forwarded to a serial bus
+
+use crate::modbus::{
+    build_rtu_frame, build_tcp_frame, default_flow_control, rtu_read_reply_length, verify_crc16,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serialport::SerialPort;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// ===================== CONSTANTS =====================
+
+/// Exception 0x0A: Gateway Path Unavailable
+pub const EXCEPTION_GATEWAY_PATH_UNAVAILABLE: u8 = 0x0A;
+
+/// Exception 0x0B: Gateway Target Device Failed to Respond
+pub const EXCEPTION_GATEWAY_TARGET_FAILED: u8 = 0x0B;
+
+// ===================== CONFIG STRUCTS =====================
+
+/// Gateway configuration: TCP listener + serial bus
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModbusGatewayConfig {
+    pub bind_address: String,
+    pub listen_port: u16,
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: String,
+    pub parity: String,
+    #[serde(default = "default_flow_control")]
+    pub flow_control: String,
+    /// Time to wait for the RTU slave before answering 0x0B
+    #[serde(default = "default_gateway_timeout")]
+    pub response_timeout_ms: u32,
+    /// Bus turnaround delay after a broadcast (unit ID 0), which no slave answers
+    #[serde(default = "default_broadcast_delay")]
+    pub broadcast_delay_ms: u32,
+}
+
+fn default_gateway_timeout() -> u32 {
+    1000
+}
+
+fn default_broadcast_delay() -> u32 {
+    100
+}
+
+// ===================== EVENT STRUCTS =====================
+
+/// One forwarded request with the frames seen on both sides
+#[derive(Debug, Serialize, Clone)]
+pub struct ModbusGatewayFrameEvent {
+    pub gateway_id: String,
+    pub client_id: String,
+    pub transaction_id: u16,
+    pub unit_id: u8,
+    pub function_code: u8,
+    pub tcp_request: Vec<u8>,
+    pub rtu_request: Vec<u8>,
+    /// None when the RTU slave did not answer
+    pub rtu_response: Option<Vec<u8>>,
+    /// Empty when nothing was returned to the TCP master (broadcast or invalid request)
+    pub tcp_response: Vec<u8>,
+    /// Gateway exception (0x0A / 0x0B) returned to the TCP master
+    pub exception_code: Option<u8>,
+    /// Request that could not be forwarded
+    pub error: Option<String>,
+    pub response_time_ms: u64,
+    pub timestamp: u64,
+}
+
+// ===================== STATE =====================
+
+/// State for managing running gateways (keyed by gateway ID)
+pub struct ModbusGatewayState {
+    pub running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    pub runtime: tokio::runtime::Runtime,
+}
+
+impl Default for ModbusGatewayState {
+    fn default() -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            runtime: tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(2)
+                .enable_all()
+                .build()
+                .expect("Failed to create Modbus Gateway Tokio runtime"),
+        }
+    }
+}
+
+// ===================== FRAME TRANSLATION =====================
+
+/// Translate an MBAP request into (transaction ID, unit ID, RTU frame)
+pub fn mbap_to_rtu(frame: &[u8]) -> Result<(u16, u8, Vec<u8>), String> {
+    if frame.len() < 8 {
+        return Err("TCP frame too short".to_string());
+    }
+
+    let transaction_id = u16::from_be_bytes([frame[0], frame[1]]);
+    let protocol_id = u16::from_be_bytes([frame[2], frame[3]]);
+    if protocol_id != 0 {
+        return Err(format!("Invalid protocol ID: {}", protocol_id));
+    }
+
+    let unit_id = frame[6];
+    Ok((transaction_id, unit_id, build_rtu_frame(unit_id, frame[7], &frame[8..])))
+}
+
+/// Translate an RTU response back into an MBAP frame
+pub fn rtu_to_mbap(transaction_id: u16, frame: &[u8]) -> Option<Vec<u8>> {
+    if frame.len() < 5 || !verify_crc16(frame) {
+        return None;
+    }
+    Some(build_tcp_frame(transaction_id, frame[0], frame[1], &frame[2..frame.len() - 2]))
+}
+
+/// MBAP exception response generated by the gateway itself
+pub fn gateway_exception(transaction_id: u16, unit_id: u8, function_code: u8, exception_code: u8) -> Vec<u8> {
+    build_tcp_frame(transaction_id, unit_id, function_code | 0x80, &[exception_code])
+}
+
+// ===================== SERIAL EXCHANGE =====================
+
+/// Send one RTU request and read the reply of the addressed slave: same function code (or its
+/// exception), with the expected length for reads. Broadcasts (unit ID 0) are not answered:
+/// Ok(None) is returned once the turnaround delay has elapsed.
+/// Errors are the exception code to return: 0x0A if the port fails, 0x0B on timeout.
+pub fn rtu_exchange(
+    port: &mut dyn SerialPort,
+    request: &[u8],
+    timeout: Duration,
+    broadcast_delay: Duration,
+) -> Result<Option<Vec<u8>>, u8> {
+    let _ = port.clear(serialport::ClearBuffer::All);
+    port.write_all(request).map_err(|_| EXCEPTION_GATEWAY_PATH_UNAVAILABLE)?;
+    port.flush().map_err(|_| EXCEPTION_GATEWAY_PATH_UNAVAILABLE)?;
+
+    if request[0] == 0 {
+        std::thread::sleep(broadcast_delay);
+        return Ok(None);
+    }
+
+    let (slave_id, function_code) = (request[0], request[1]);
+    let expected_len = rtu_read_reply_length(request);
+    let deadline = Instant::now() + timeout;
+    let mut response: Vec<u8> = Vec::with_capacity(256);
+    let mut buffer = [0u8; 256];
+
+    while Instant::now() < deadline {
+        match port.read(&mut buffer) {
+            Ok(n) => {
+                response.extend_from_slice(&buffer[..n]);
+
+                // Skip bytes that cannot start the reply (noise, other slaves)
+                while !response.is_empty()
+                    && (response[0] != slave_id
+                        || response.get(1).is_some_and(|&fc| fc != function_code && fc != function_code | 0x80))
+                {
+                    response.remove(0);
+                }
+                if response.len() < 5 {
+                    continue;
+                }
+
+                let len = if response[1] & 0x80 != 0 {
+                    Some(5)
+                } else {
+                    expected_len
+                };
+                match len {
+                    Some(len) if response.len() >= len && verify_crc16(&response[..len]) => {
+                        response.truncate(len);
+                        return Ok(Some(response));
+                    }
+                    Some(_) => {}
+                    None if verify_crc16(&response) => return Ok(Some(response)),
+                    None => {}
+                }
+                if response.len() > 256 {
+                    break;
+                }
+            }
+            Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+            Err(_) => return Err(EXCEPTION_GATEWAY_PATH_UNAVAILABLE),
+        }
+    }
+
+    Err(EXCEPTION_GATEWAY_TARGET_FAILED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serial::mock::MockSerialPort;
+
+    #[test]
+    fn test_mbap_rtu_translation() {
+        // Read 10 holding registers from unit 1, transaction 0x0102
+        let tcp = build_tcp_frame(0x0102, 0x01, 0x03, &[0x00, 0x00, 0x00, 0x0A]);
+        let (transaction_id, unit_id, rtu) = mbap_to_rtu(&tcp).unwrap();
+        assert_eq!((transaction_id, unit_id), (0x0102, 1));
+        assert_eq!(rtu, vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD]);
+
+        let reply = build_rtu_frame(0x01, 0x03, &[0x02, 0x00, 0x2A]);
+        assert_eq!(
+            rtu_to_mbap(0x0102, &reply).unwrap(),
+            build_tcp_frame(0x0102, 0x01, 0x03, &[0x02, 0x00, 0x2A])
+        );
+        assert!(rtu_to_mbap(0x0102, &reply[..4]).is_none());
+
+        assert_eq!(
+            gateway_exception(7, 3, 0x03, EXCEPTION_GATEWAY_TARGET_FAILED),
+            vec![0x00, 0x07, 0x00, 0x00, 0x00, 0x03, 0x03, 0x83, 0x0B]
+        );
+    }
+
+    #[test]
+    fn test_rtu_exchange_reply_matching() {
+        let request = build_rtu_frame(0x01, 0x03, &[0x00, 0x00, 0x00, 0x02]);
+        let reply = build_rtu_frame(0x01, 0x03, &[0x04, 0x00, 0x2A, 0x00, 0x2B]);
+
+        // Another slave's frame and trailing noise are not taken as the reply
+        let mut port = MockSerialPort::new(Duration::from_millis(5));
+        port.reads.push_back(build_rtu_frame(0x02, 0x06, &[0x00, 0x01, 0x00, 0x01]));
+        port.reads.push_back([reply.clone(), vec![0xFF]].concat());
+        let result = rtu_exchange(&mut port, &request, Duration::from_millis(100), Duration::ZERO);
+        assert_eq!(result, Ok(Some(reply)));
+        assert_eq!(port.written, request);
+
+        // Same slave answering another function code: target failed to respond
+        let mut port = MockSerialPort::new(Duration::from_millis(5));
+        port.reads.push_back(build_rtu_frame(0x01, 0x04, &[0x04, 0x00, 0x2A, 0x00, 0x2B]));
+        let result = rtu_exchange(&mut port, &request, Duration::from_millis(30), Duration::ZERO);
+        assert_eq!(result, Err(EXCEPTION_GATEWAY_TARGET_FAILED));
+
+        // Broadcast: written, never answered
+        let broadcast = build_rtu_frame(0x00, 0x06, &[0x00, 0x01, 0x00, 0x01]);
+        let mut port = MockSerialPort::new(Duration::from_millis(5));
+        let result = rtu_exchange(&mut port, &broadcast, Duration::from_millis(100), Duration::from_millis(1));
+        assert_eq!(result, Ok(None));
+        assert_eq!(port.written, broadcast);
+    }
+}
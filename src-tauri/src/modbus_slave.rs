@@ -2,20 +2,26 @@
 // Supports RTU (Serial) and TCP/IP server modes
 
 use crate::modbus::{
-    build_rtu_frame, build_tcp_frame, format_exception_error, get_timestamp,
-    verify_crc16, FunctionCode, ModbusMode,
+    ascii_to_rtu_frame, build_ascii_frame, build_rtu_frame, build_tcp_frame, default_flow_control,
+    default_serial_mode, device_id_object_name, format_exception_error, get_timestamp,
+    verify_crc16, DeviceIdObject, FunctionCode, ModbusMode,
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 // ===================== CONSTANTS =====================
 
 /// Default data storage size per type
 const DEFAULT_DATA_SIZE: usize = 10000;
 
+/// Max PDU size (FC + data)
+const MAX_PDU_SIZE: usize = 253;
+
 // ===================== DATA STRUCTURES =====================
 
 /// Modbus data storage for a single slave instance
@@ -52,6 +58,17 @@ pub struct ModbusSlaveRtuConfig {
     pub stop_bits: String,
     pub parity: String,
     pub slave_id: u8,
+    #[serde(default = "default_flow_control")]
+    pub flow_control: String, // "none", "rts_cts", "xon_xoff"
+    /// Serial framing: "rtu" (default) or "ascii"
+    #[serde(default = "default_serial_mode")]
+    pub mode: ModbusMode,
+    /// More slave IDs simulated on the same port, each with its own data bank
+    #[serde(default)]
+    pub extra_slave_ids: Vec<u8>,
+    /// Apply write requests sent to ID 0 on every simulated slave (no reply)
+    #[serde(default)]
+    pub broadcast: bool,
 }
 
 /// Modbus Slave TCP configuration
@@ -60,6 +77,12 @@ pub struct ModbusSlaveTcpConfig {
     pub listen_port: u16,
     pub bind_address: String,
     pub unit_id: u8,
+    /// Raw RTU frames (with CRC) over the socket instead of MBAP
+    #[serde(default)]
+    pub rtu_over_tcp: bool,
+    /// More unit IDs to answer, each with its own data bank (gateway emulation)
+    #[serde(default)]
+    pub extra_unit_ids: Vec<u8>,
 }
 
 /// Unified slave configuration
@@ -67,6 +90,7 @@ pub struct ModbusSlaveTcpConfig {
 pub enum ModbusSlaveConfig {
     Rtu(ModbusSlaveRtuConfig),
     Tcp(ModbusSlaveTcpConfig),
+    RtuOverTcp(ModbusSlaveTcpConfig),
 }
 
 // ===================== SIMULATION TYPES =====================
@@ -102,11 +126,209 @@ pub enum SimulationType {
 /// Simulation configuration for a register
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationConfig {
+    /// Simulated unit (None = the configured slave / unit ID)
+    #[serde(default)]
+    pub unit_id: Option<u8>,
     pub data_type: String, // "coil", "holding_register", etc.
     pub address: u16,
     pub simulation: SimulationType,
 }
 
+/// Tick interval of the simulation engine (ms)
+pub const SIMULATION_TICK_MS: u64 = 50;
+
+/// Runtime state of one simulation, kept by the engine between ticks
+#[derive(Debug, Clone, Default)]
+pub struct SimulationRuntime {
+    pub started_at: u64,
+    pub last_update: u64,
+    pub current: Option<u16>,
+    pub descending: bool,
+}
+
+impl SimulationType {
+    /// Compute the next value, or None if nothing is due yet
+    pub fn next_value(&self, runtime: &mut SimulationRuntime, now: u64) -> Option<u16> {
+        if runtime.started_at == 0 {
+            runtime.started_at = now;
+        }
+
+        match self {
+            SimulationType::None => None,
+            SimulationType::SinWave { min, max, period_ms } => {
+                if *period_ms == 0 || now.saturating_sub(runtime.last_update) < SIMULATION_TICK_MS {
+                    return None;
+                }
+                runtime.last_update = now;
+
+                let (lo, hi) = (*min.min(max) as f64, *min.max(max) as f64);
+                let elapsed = (now - runtime.started_at) as f64;
+                let phase = 2.0 * std::f64::consts::PI * elapsed / *period_ms as f64;
+                let value = lo + (hi - lo) * (1.0 + phase.sin()) / 2.0;
+                Some(value.round() as u16)
+            }
+            SimulationType::Ramp {
+                min,
+                max,
+                step,
+                interval_ms,
+                reverse_at_bounds,
+            } => {
+                if now.saturating_sub(runtime.last_update) < (*interval_ms as u64).max(SIMULATION_TICK_MS) {
+                    return None;
+                }
+                runtime.last_update = now;
+
+                let (lo, hi) = (*min.min(max) as i32, *min.max(max) as i32);
+                let Some(current) = runtime.current else {
+                    let start = if *step >= 0 { lo } else { hi };
+                    runtime.current = Some(start as u16);
+                    return runtime.current;
+                };
+
+                let delta = if runtime.descending { -(*step as i32) } else { *step as i32 };
+                let mut next = current as i32 + delta;
+
+                if next > hi || next < lo {
+                    if *reverse_at_bounds {
+                        runtime.descending = !runtime.descending;
+                        next = next.clamp(lo, hi);
+                    } else if next > hi {
+                        next = lo;
+                    } else {
+                        next = hi;
+                    }
+                }
+
+                runtime.current = Some(next as u16);
+                runtime.current
+            }
+            SimulationType::Random { min, max, interval_ms } => {
+                if now.saturating_sub(runtime.last_update) < (*interval_ms as u64).max(SIMULATION_TICK_MS) {
+                    return None;
+                }
+                runtime.last_update = now;
+
+                let (lo, hi) = (*min.min(max) as u32, *min.max(max) as u32);
+                Some((lo + rand_u32() % (hi - lo + 1)) as u16)
+            }
+        }
+    }
+}
+
+/// Check that a simulation targets a valid data type and address
+pub fn validate_simulation(config: &SimulationConfig) -> Result<(), String> {
+    if !matches!(
+        config.data_type.as_str(),
+        "coil" | "discrete_input" | "holding_register" | "input_register"
+    ) {
+        return Err(format!("Invalid data type: {}", config.data_type));
+    }
+    if config.address as usize >= DEFAULT_DATA_SIZE {
+        return Err("Address out of range".to_string());
+    }
+    match config.simulation {
+        SimulationType::SinWave { period_ms: 0, .. } => {
+            Err("Period must be greater than 0".to_string())
+        }
+        SimulationType::Ramp { step: 0, .. } => Err("Step must not be 0".to_string()),
+        _ => Ok(()),
+    }
+}
+
+// ===================== DATA-CHANGE RULES =====================
+
+/// Rule run inside the slave when a master write (or another rule) changes its data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlaveRule {
+    /// When `address` is written (with `equals`, if set), store `set_value` at the target
+    OnWrite {
+        data_type: String,
+        address: u16,
+        #[serde(default)]
+        equals: Option<u16>,
+        target_type: String,
+        target_address: u16,
+        set_value: u16,
+    },
+    /// Copy writes in `start_address..=end_address` to the same offsets of the target range
+    Mirror {
+        data_type: String,
+        start_address: u16,
+        end_address: u16,
+        target_type: String,
+        target_start_address: u16,
+    },
+    /// Reset `address` to `reset_value` `delay_ms` after it was written (command registers)
+    AutoClear {
+        data_type: String,
+        address: u16,
+        delay_ms: u32,
+        #[serde(default)]
+        reset_value: u16,
+    },
+}
+
+/// Chained rule evaluations per master write (guards against rules triggering each other forever)
+pub const MAX_RULE_DEPTH: usize = 8;
+
+/// Auto-clear scheduled by a write (ordered by due time)
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PendingAutoClear {
+    pub due_at: Instant,
+    /// Write generation of the point when scheduled: a later write supersedes the clear
+    pub generation: u64,
+    pub data_type: String,
+    pub address: u16,
+    pub reset_value: u16,
+}
+
+/// Runtime state of a unit's rules. Its lock is held across a request and the rules it
+/// triggers, so a write and its rule effects are applied atomically.
+#[derive(Debug, Default)]
+pub struct RuleRuntime {
+    /// Write generation per (data type, address) of auto-clear points, bumped on every write
+    generations: HashMap<(String, u16), u64>,
+    /// Scheduled auto-clears, earliest first
+    pending_clears: BinaryHeap<Reverse<PendingAutoClear>>,
+}
+
+/// Check that a rule targets valid data types and addresses
+pub fn validate_rule(rule: &SlaveRule) -> Result<(), String> {
+    let check = |data_type: &str, address: u16| -> Result<(), String> {
+        if !matches!(data_type, "coil" | "discrete_input" | "holding_register" | "input_register") {
+            return Err(format!("Invalid data type: {}", data_type));
+        }
+        if address as usize >= DEFAULT_DATA_SIZE {
+            return Err("Address out of range".to_string());
+        }
+        Ok(())
+    };
+
+    match rule {
+        SlaveRule::OnWrite { data_type, address, target_type, target_address, .. } => {
+            check(data_type, *address)?;
+            check(target_type, *target_address)
+        }
+        SlaveRule::Mirror { data_type, start_address, end_address, target_type, target_start_address } => {
+            if end_address < start_address {
+                return Err("End address must not be below start address".to_string());
+            }
+            check(data_type, *start_address)?;
+            check(data_type, *end_address)?;
+            check(target_type, *target_start_address)?;
+            check(target_type, target_start_address.saturating_add(end_address - start_address))
+        }
+        SlaveRule::AutoClear { data_type, address, delay_ms, .. } => {
+            if *delay_ms == 0 {
+                return Err("Delay must be greater than 0".to_string());
+            }
+            check(data_type, *address)
+        }
+    }
+}
+
 // ===================== EXCEPTION MAPPING =====================
 
 /// Exception mapping for testing error responses
@@ -118,6 +340,91 @@ pub struct ExceptionMapping {
     pub exception_code: u8,
 }
 
+// ===================== DEVICE IDENTIFICATION =====================
+
+/// Default object table answered for FC43/14
+pub fn default_device_identification() -> Vec<DeviceIdObject> {
+    [(0x00, "TermiPro"), (0x01, "TP-SLAVE"), (0x02, env!("CARGO_PKG_VERSION"))]
+        .into_iter()
+        .map(|(id, value)| DeviceIdObject {
+            id,
+            name: device_id_object_name(id),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+/// Build the FC43/14 response PDU data (after FC) from an object table.
+/// Returns Err(exception_code) on invalid requests.
+pub fn build_device_identification_data(
+    objects: &[DeviceIdObject],
+    read_device_id_code: u8,
+    object_id: u8,
+) -> Result<Vec<u8>, u8> {
+    let mut sorted: Vec<&DeviceIdObject> = objects.iter().collect();
+    sorted.sort_by_key(|o| o.id);
+
+    let conformity_level = match sorted.last().map(|o| o.id) {
+        Some(0x80..=0xFF) => 0x83,
+        Some(0x03..=0x7F) => 0x82,
+        _ => 0x81,
+    };
+
+    let selected: Vec<&DeviceIdObject> = match read_device_id_code {
+        0x01..=0x03 => {
+            let max_id = match read_device_id_code {
+                0x01 => 0x02,
+                0x02 => 0x7F,
+                _ => 0xFF,
+            };
+            let in_category: Vec<&DeviceIdObject> =
+                sorted.into_iter().filter(|o| o.id <= max_id).collect();
+            // Unknown start object: restart from the beginning of the category
+            let start = if in_category.iter().any(|o| o.id == object_id) { object_id } else { 0 };
+            in_category.into_iter().filter(|o| o.id >= start).collect()
+        }
+        0x04 => {
+            let object = sorted
+                .into_iter()
+                .find(|o| o.id == object_id)
+                .ok_or(0x02u8)?;
+            vec![object]
+        }
+        _ => return Err(0x03),
+    };
+
+    // FC + MEI type + code + conformity + more follows + next object + count
+    let mut size = 7;
+    let mut body = Vec::new();
+    let mut count: u8 = 0;
+    let mut next_object_id = 0;
+    for object in &selected {
+        let value = object.value.as_bytes();
+        let value = &value[..value.len().min(MAX_PDU_SIZE - 9)];
+        if size + 2 + value.len() > MAX_PDU_SIZE {
+            next_object_id = object.id;
+            break;
+        }
+        body.push(object.id);
+        body.push(value.len() as u8);
+        body.extend_from_slice(value);
+        size += 2 + value.len();
+        count += 1;
+    }
+    let more_follows = (count as usize) < selected.len();
+
+    let mut data = vec![
+        0x0E,
+        read_device_id_code,
+        conformity_level,
+        if more_follows { 0xFF } else { 0x00 },
+        if more_follows { next_object_id } else { 0x00 },
+        count,
+    ];
+    data.extend_from_slice(&body);
+    Ok(data)
+}
+
 // ===================== RESPONSE DELAY =====================
 
 /// Response delay configuration
@@ -216,6 +523,186 @@ pub struct ModbusSlaveTcpClient {
     pub request_count: u64,
 }
 
+// ===================== UNITS =====================
+
+/// Data bank, exception mappings and delay config of one unit (slave ID).
+/// Extra units let one listener / port answer for several slave IDs.
+#[derive(Clone, Default)]
+pub struct SlaveUnit {
+    pub data: Arc<ModbusSlaveData>,
+    pub exception_mappings: Arc<RwLock<Vec<ExceptionMapping>>>,
+    pub delay_config: Arc<RwLock<ResponseDelayConfig>>,
+    pub rules: Arc<RwLock<Vec<SlaveRule>>>,
+    pub rule_runtime: Arc<Mutex<RuleRuntime>>,
+}
+
+impl SlaveUnit {
+    /// Calculate delay for a request
+    pub fn get_delay_ms(&self, fc: u8) -> u32 {
+        let config = self.delay_config.read();
+        let mut delay = config.global_delay_ms;
+
+        // Add per-FC delay
+        if let Some(&fc_delay) = config.fc_delays.get(&fc) {
+            delay += fc_delay;
+        }
+
+        // Add random delay
+        if let Some((min, max)) = config.random_delay {
+            if max > min {
+                delay += min + (rand_u32() % (max - min));
+            }
+        }
+
+        delay
+    }
+
+    /// Check for exception mapping
+    pub fn get_exception(&self, data_type: &str, address: u16) -> Option<u8> {
+        let mappings = self.exception_mappings.read();
+        for mapping in mappings.iter() {
+            if mapping.data_type == data_type
+                && address >= mapping.start_address
+                && address <= mapping.end_address
+            {
+                return Some(mapping.exception_code);
+            }
+        }
+        None
+    }
+
+    /// Process a request on this unit and run the rules triggered by its write under the
+    /// unit's rule lock, so other masters never observe a write without its rule effects
+    pub fn process(
+        &self,
+        request: &ParsedRtuRequest,
+        mode: ModbusMode,
+        transaction_id: u16,
+        connection_id: &str,
+        handle: &ModbusSlaveHandle,
+    ) -> (ProcessedRequest, Vec<ModbusSlaveDataChangedEvent>) {
+        let mut runtime = self.rule_runtime.lock();
+        let result = process_request(request, &self.data, mode, transaction_id, connection_id, handle);
+        let rule_changes = match &result.data_changed {
+            Some(change) => self.apply_rules(&mut runtime, change),
+            None => Vec::new(),
+        };
+        (result, rule_changes)
+    }
+
+    /// Run the data-change rules for a write and store their results.
+    /// Changes made by rules are fed back to the rules, up to `MAX_RULE_DEPTH` levels.
+    fn apply_rules(
+        &self,
+        runtime: &mut RuleRuntime,
+        change: &ModbusSlaveDataChangedEvent,
+    ) -> Vec<ModbusSlaveDataChangedEvent> {
+        let rules = self.rules.read();
+        let mut changes = Vec::new();
+        let mut queue = VecDeque::from([(change.clone(), 0usize)]);
+
+        while let Some((change, depth)) = queue.pop_front() {
+            for (offset, &value) in change.values.iter().enumerate() {
+                let address = change.start_address.saturating_add(offset as u16);
+
+                for rule in rules.iter() {
+                    let target = match rule {
+                        SlaveRule::OnWrite { data_type, address: rule_address, equals, target_type, target_address, set_value }
+                            if *data_type == change.data_type
+                                && *rule_address == address
+                                && equals.is_none_or(|e| e == value) =>
+                        {
+                            Some((target_type, *target_address, *set_value))
+                        }
+                        SlaveRule::Mirror { data_type, start_address, end_address, target_type, target_start_address }
+                            if *data_type == change.data_type && (*start_address..=*end_address).contains(&address) =>
+                        {
+                            Some((target_type, target_start_address + (address - start_address), value))
+                        }
+                        SlaveRule::AutoClear { data_type, address: rule_address, delay_ms, reset_value }
+                            if *data_type == change.data_type && *rule_address == address =>
+                        {
+                            // Every write restarts the delay: earlier clears of this point are superseded
+                            let generation = runtime.generations.entry((data_type.clone(), address)).or_default();
+                            *generation += 1;
+                            if value != *reset_value {
+                                runtime.pending_clears.push(Reverse(PendingAutoClear {
+                                    due_at: Instant::now() + Duration::from_millis(*delay_ms as u64),
+                                    generation: *generation,
+                                    data_type: data_type.clone(),
+                                    address,
+                                    reset_value: *reset_value,
+                                }));
+                            }
+                            None
+                        }
+                        _ => None,
+                    };
+
+                    let Some((target_type, target_address, target_value)) = target else {
+                        continue;
+                    };
+                    // Only actual changes are reported (and chained)
+                    if let Some(stored) = self.data.set_value(target_type, target_address, target_value) {
+                        let derived = ModbusSlaveDataChangedEvent {
+                            connection_id: change.connection_id.clone(),
+                            unit_id: change.unit_id,
+                            data_type: target_type.clone(),
+                            start_address: target_address,
+                            values: vec![stored],
+                            timestamp: get_timestamp(),
+                        };
+                        if depth < MAX_RULE_DEPTH {
+                            queue.push_back((derived.clone(), depth + 1));
+                        }
+                        changes.push(derived);
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Execute the auto-clears due at `now` whose point was not written since they were scheduled.
+    /// Returns the clears and the changes of the rules they trigger.
+    pub fn run_due_clears(
+        &self,
+        connection_id: &str,
+        unit_id: u8,
+        now: Instant,
+    ) -> Vec<ModbusSlaveDataChangedEvent> {
+        let mut runtime = self.rule_runtime.lock();
+        let mut events = Vec::new();
+
+        while runtime.pending_clears.peek().is_some_and(|Reverse(p)| p.due_at <= now) {
+            let Some(Reverse(pending)) = runtime.pending_clears.pop() else {
+                break;
+            };
+            let key = (pending.data_type.clone(), pending.address);
+            if runtime.generations.get(&key) != Some(&pending.generation) {
+                continue;
+            }
+
+            if let Some(stored) = self.data.set_value(&pending.data_type, pending.address, pending.reset_value) {
+                let cleared = ModbusSlaveDataChangedEvent {
+                    connection_id: connection_id.to_string(),
+                    unit_id,
+                    data_type: pending.data_type,
+                    start_address: pending.address,
+                    values: vec![stored],
+                    timestamp: get_timestamp(),
+                };
+                let rule_changes = self.apply_rules(&mut runtime, &cleared);
+                events.push(cleared);
+                events.extend(rule_changes);
+            }
+        }
+
+        events
+    }
+}
+
 // ===================== CONNECTION HANDLE =====================
 
 /// Slave connection handle
@@ -224,15 +711,20 @@ pub struct ModbusSlaveHandle {
     pub mode: ModbusMode,
     pub config: ModbusSlaveConfig,
     pub data: Arc<ModbusSlaveData>,
-    pub running: AtomicBool,
+    pub running: Arc<AtomicBool>,
     pub request_count: AtomicU64,
     pub last_request_time: AtomicU64,
 
     // Advanced features
     pub simulations: RwLock<Vec<SimulationConfig>>,
-    pub exception_mappings: RwLock<Vec<ExceptionMapping>>,
-    pub delay_config: RwLock<ResponseDelayConfig>,
+    pub exception_mappings: Arc<RwLock<Vec<ExceptionMapping>>>,
+    pub delay_config: Arc<RwLock<ResponseDelayConfig>>,
+    pub rules: Arc<RwLock<Vec<SlaveRule>>>,
+    pub rule_runtime: Arc<Mutex<RuleRuntime>>,
     pub statistics: RwLock<SlaveStatistics>,
+    pub device_identification: RwLock<Vec<DeviceIdObject>>,
+    /// Units answered besides the configured slave / unit ID
+    pub units: RwLock<HashMap<u8, SlaveUnit>>,
 
     // For TCP: connected clients
     pub tcp_clients: Option<Arc<RwLock<HashMap<String, ModbusSlaveTcpClient>>>>,
@@ -240,42 +732,66 @@ pub struct ModbusSlaveHandle {
 
 impl ModbusSlaveHandle {
     pub fn new_rtu(config: ModbusSlaveRtuConfig) -> Self {
+        let units = config
+            .extra_slave_ids
+            .iter()
+            .filter(|&&id| id != 0 && id != config.slave_id)
+            .map(|&id| (id, SlaveUnit::default()))
+            .collect();
         Self {
-            mode: ModbusMode::Rtu,
+            mode: config.mode,
             config: ModbusSlaveConfig::Rtu(config),
             data: Arc::new(ModbusSlaveData::default()),
-            running: AtomicBool::new(true),
+            running: Arc::new(AtomicBool::new(true)),
             request_count: AtomicU64::new(0),
             last_request_time: AtomicU64::new(0),
             simulations: RwLock::new(Vec::new()),
-            exception_mappings: RwLock::new(Vec::new()),
-            delay_config: RwLock::new(ResponseDelayConfig::default()),
+            exception_mappings: Arc::new(RwLock::new(Vec::new())),
+            delay_config: Arc::new(RwLock::new(ResponseDelayConfig::default())),
+            rules: Arc::new(RwLock::new(Vec::new())),
+            rule_runtime: Arc::new(Mutex::new(RuleRuntime::default())),
             statistics: RwLock::new(SlaveStatistics::default()),
+            device_identification: RwLock::new(default_device_identification()),
+            units: RwLock::new(units),
             tcp_clients: None,
         }
     }
 
     pub fn new_tcp(config: ModbusSlaveTcpConfig) -> Self {
+        let units = config
+            .extra_unit_ids
+            .iter()
+            .filter(|&&id| id != config.unit_id)
+            .map(|&id| (id, SlaveUnit::default()))
+            .collect();
+        let (mode, config) = if config.rtu_over_tcp {
+            (ModbusMode::Rtu, ModbusSlaveConfig::RtuOverTcp(config))
+        } else {
+            (ModbusMode::Tcp, ModbusSlaveConfig::Tcp(config))
+        };
         Self {
-            mode: ModbusMode::Tcp,
-            config: ModbusSlaveConfig::Tcp(config),
+            mode,
+            config,
             data: Arc::new(ModbusSlaveData::default()),
-            running: AtomicBool::new(true),
+            running: Arc::new(AtomicBool::new(true)),
             request_count: AtomicU64::new(0),
             last_request_time: AtomicU64::new(0),
             simulations: RwLock::new(Vec::new()),
-            exception_mappings: RwLock::new(Vec::new()),
-            delay_config: RwLock::new(ResponseDelayConfig::default()),
+            exception_mappings: Arc::new(RwLock::new(Vec::new())),
+            delay_config: Arc::new(RwLock::new(ResponseDelayConfig::default())),
+            rules: Arc::new(RwLock::new(Vec::new())),
+            rule_runtime: Arc::new(Mutex::new(RuleRuntime::default())),
             statistics: RwLock::new(SlaveStatistics::default()),
+            device_identification: RwLock::new(default_device_identification()),
+            units: RwLock::new(units),
             tcp_clients: Some(Arc::new(RwLock::new(HashMap::new()))),
         }
     }
 
-    #[allow(dead_code)]
     pub fn get_slave_id(&self) -> u8 {
         match &self.config {
             ModbusSlaveConfig::Rtu(c) => c.slave_id,
-            ModbusSlaveConfig::Tcp(c) => c.unit_id,
+            ModbusSlaveConfig::Tcp(c) | ModbusSlaveConfig::RtuOverTcp(c) => c.unit_id,
         }
     }
 
@@ -293,38 +809,130 @@ impl ModbusSlaveHandle {
             .store(get_timestamp(), Ordering::SeqCst);
     }
 
-    /// Calculate delay for a request
-    pub fn get_delay_ms(&self, fc: u8) -> u32 {
-        let config = self.delay_config.read();
-        let mut delay = config.global_delay_ms;
+    /// The configured slave / unit ID as a unit
+    pub fn primary_unit(&self) -> SlaveUnit {
+        SlaveUnit {
+            data: self.data.clone(),
+            exception_mappings: self.exception_mappings.clone(),
+            delay_config: self.delay_config.clone(),
+            rules: self.rules.clone(),
+            rule_runtime: self.rule_runtime.clone(),
+        }
+    }
 
-        // Add per-FC delay
-        if let Some(&fc_delay) = config.fc_delays.get(&fc) {
-            delay += fc_delay;
+    /// Look up a unit by ID (None = the configured slave / unit ID)
+    pub fn unit(&self, unit_id: Option<u8>) -> Option<SlaveUnit> {
+        match unit_id {
+            None => Some(self.primary_unit()),
+            Some(id) if id == self.get_slave_id() => Some(self.primary_unit()),
+            Some(id) => self.units.read().get(&id).cloned(),
         }
+    }
 
-        // Add random delay
-        if let Some((min, max)) = config.random_delay {
-            if max > min {
-                delay += min + (rand_u32() % (max - min));
+    /// Add an extra unit with its own empty data bank
+    pub fn add_unit(&self, unit_id: u8) -> Result<(), String> {
+        if unit_id == 0 && self.mode != ModbusMode::Tcp {
+            return Err("ID 0 is the RTU broadcast address".to_string());
+        }
+        if unit_id == self.get_slave_id() || self.units.read().contains_key(&unit_id) {
+            return Err(format!("Unit {} already exists", unit_id));
+        }
+        self.units.write().insert(unit_id, SlaveUnit::default());
+        Ok(())
+    }
+
+    /// Broadcast (ID 0) handling enabled (RTU / ASCII only)
+    pub fn broadcast_enabled(&self) -> bool {
+        matches!(&self.config, ModbusSlaveConfig::Rtu(c) if c.broadcast)
+    }
+
+    /// All unit IDs answered by this slave (sorted)
+    pub fn unit_ids(&self) -> Vec<u8> {
+        let mut ids: Vec<u8> = self.units.read().keys().copied().collect();
+        ids.push(self.get_slave_id());
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Apply a broadcast write (ID 0) to every simulated slave and return the data changes
+    /// (including rule effects). Broadcasts are never answered, so no response is built.
+    pub fn apply_broadcast(
+        &self,
+        request: &ParsedRtuRequest,
+        connection_id: &str,
+    ) -> Vec<ModbusSlaveDataChangedEvent> {
+        let mut events = Vec::new();
+        for id in self.unit_ids() {
+            if let Some(unit) = self.unit(Some(id)) {
+                let unit_request = ParsedRtuRequest { slave_id: id, ..request.clone() };
+                let (result, rule_changes) = unit.process(&unit_request, self.mode, 0, connection_id, self);
+                if let Some(change) = result.data_changed {
+                    events.push(change);
+                    events.extend(rule_changes);
+                }
             }
         }
 
-        delay
+        self.increment_request_count();
+        self.statistics.write().record_request(request.function_code, true, 0);
+        events
     }
 
-    /// Check for exception mapping
-    pub fn get_exception(&self, data_type: &str, address: u16) -> Option<u8> {
-        let mappings = self.exception_mappings.read();
-        for mapping in mappings.iter() {
-            if mapping.data_type == data_type
-                && address >= mapping.start_address
-                && address <= mapping.end_address
-            {
-                return Some(mapping.exception_code);
+    /// Advance all configured simulations and return the resulting data changes.
+    /// Runtime state is keyed by (unit ID, data type, address).
+    pub fn run_simulations(
+        &self,
+        runtimes: &mut HashMap<(u8, String, u16), SimulationRuntime>,
+        connection_id: &str,
+        now: u64,
+    ) -> Vec<ModbusSlaveDataChangedEvent> {
+        let simulations = self.simulations.read();
+        let primary_id = self.get_slave_id();
+
+        // Drop runtime state of removed simulations
+        runtimes.retain(|(unit_id, data_type, address), _| {
+            simulations.iter().any(|s| {
+                s.unit_id.unwrap_or(primary_id) == *unit_id && &s.data_type == data_type && s.address == *address
+            })
+        });
+
+        let mut events = Vec::new();
+        for sim in simulations.iter() {
+            let unit_id = sim.unit_id.unwrap_or(primary_id);
+            // Unit removed since the simulation was added
+            let Some(unit) = self.unit(Some(unit_id)) else {
+                continue;
+            };
+            let runtime = runtimes
+                .entry((unit_id, sim.data_type.clone(), sim.address))
+                .or_default();
+
+            if let Some(value) = sim.simulation.next_value(runtime, now) {
+                if let Some(stored) = unit.data.set_value(&sim.data_type, sim.address, value) {
+                    events.push(ModbusSlaveDataChangedEvent {
+                        connection_id: connection_id.to_string(),
+                        unit_id,
+                        data_type: sim.data_type.clone(),
+                        start_address: sim.address,
+                        values: vec![stored],
+                        timestamp: now,
+                    });
+                }
             }
         }
-        None
+        events
+    }
+
+    /// Run the due auto-clears of every unit (driven by the simulation engine tick,
+    /// which stops with the slave)
+    pub fn run_auto_clears(&self, connection_id: &str, now: Instant) -> Vec<ModbusSlaveDataChangedEvent> {
+        let mut events = self
+            .primary_unit()
+            .run_due_clears(connection_id, self.get_slave_id(), now);
+        for (&unit_id, unit) in self.units.read().iter() {
+            events.extend(unit.run_due_clears(connection_id, unit_id, now));
+        }
+        events
     }
 }
 
@@ -370,6 +978,8 @@ pub struct ModbusSlaveRequestEvent {
 #[derive(Debug, Clone, Serialize)]
 pub struct ModbusSlaveDataChangedEvent {
     pub connection_id: String,
+    /// Unit / slave ID whose data bank changed
+    pub unit_id: u8,
     pub data_type: String, // "coil", "holding_register"
     pub start_address: u16,
     pub values: Vec<u16>, // coils as 0/1
@@ -398,7 +1008,33 @@ pub struct ProcessedRequest {
     pub quantity: u16,
 }
 
+impl ProcessedRequest {
+    /// Exception answer for a request that is not processed (e.g. unknown unit ID)
+    pub fn exception(
+        request: &ParsedRtuRequest,
+        exception_code: u8,
+        mode: ModbusMode,
+        transaction_id: u16,
+    ) -> Self {
+        Self {
+            response_frame: build_exception_response(
+                request.slave_id,
+                request.function_code,
+                exception_code,
+                mode,
+                transaction_id,
+            ),
+            success: false,
+            error_message: Some(format_exception_error(exception_code)),
+            data_changed: None,
+            start_address: request.start_address,
+            quantity: request.quantity,
+        }
+    }
+}
+
 /// Parse RTU request and extract fields
+#[derive(Clone)]
 pub struct ParsedRtuRequest {
     pub slave_id: u8,
     pub function_code: u8,
@@ -406,11 +1042,20 @@ pub struct ParsedRtuRequest {
     pub quantity: u16,
     pub write_values: Option<Vec<u16>>,
     pub coil_values: Option<Vec<bool>>,
+    /// FC23: write start address
+    pub write_address: Option<u16>,
+    /// Raw PDU data after the function code (FC08, FC43)
+    pub pdu_data: Vec<u8>,
+}
+
+/// Function codes a broadcast (ID 0) request may carry: writes only
+pub fn is_broadcast_function(fc: u8) -> bool {
+    matches!(fc, 0x05 | 0x06 | 0x0F | 0x10 | 0x16)
 }
 
 /// Parse incoming RTU request frame
 pub fn parse_rtu_request(frame: &[u8]) -> Result<ParsedRtuRequest, String> {
-    if frame.len() < 8 {
+    if frame.len() < 4 {
         return Err("Frame too short".to_string());
     }
 
@@ -418,104 +1063,17 @@ pub fn parse_rtu_request(frame: &[u8]) -> Result<ParsedRtuRequest, String> {
         return Err("CRC error".to_string());
     }
 
-    let slave_id = frame[0];
-    let fc = frame[1];
-    let start_address = u16::from_be_bytes([frame[2], frame[3]]);
-
-    match FunctionCode::from_u8(fc) {
-        Some(FunctionCode::ReadCoils)
-        | Some(FunctionCode::ReadDiscreteInputs)
-        | Some(FunctionCode::ReadHoldingRegisters)
-        | Some(FunctionCode::ReadInputRegisters) => {
-            let quantity = u16::from_be_bytes([frame[4], frame[5]]);
-            Ok(ParsedRtuRequest {
-                slave_id,
-                function_code: fc,
-                start_address,
-                quantity,
-                write_values: None,
-                coil_values: None,
-            })
-        }
-        Some(FunctionCode::WriteSingleCoil) => {
-            let value = u16::from_be_bytes([frame[4], frame[5]]);
-            let coil = value == 0xFF00;
-            Ok(ParsedRtuRequest {
-                slave_id,
-                function_code: fc,
-                start_address,
-                quantity: 1,
-                write_values: None,
-                coil_values: Some(vec![coil]),
-            })
-        }
-        Some(FunctionCode::WriteSingleRegister) => {
-            let value = u16::from_be_bytes([frame[4], frame[5]]);
-            Ok(ParsedRtuRequest {
-                slave_id,
-                function_code: fc,
-                start_address,
-                quantity: 1,
-                write_values: Some(vec![value]),
-                coil_values: None,
-            })
-        }
-        Some(FunctionCode::WriteMultipleCoils) => {
-            let quantity = u16::from_be_bytes([frame[4], frame[5]]);
-            let byte_count = frame[6] as usize;
-
-            if frame.len() < 9 + byte_count {
-                return Err("Incomplete write multiple coils request".to_string());
-            }
-
-            let mut coils = Vec::with_capacity(quantity as usize);
-            for i in 0..quantity as usize {
-                let byte_idx = 7 + (i / 8);
-                let bit_idx = i % 8;
-                let coil = (frame[byte_idx] >> bit_idx) & 1 == 1;
-                coils.push(coil);
-            }
-
-            Ok(ParsedRtuRequest {
-                slave_id,
-                function_code: fc,
-                start_address,
-                quantity,
-                write_values: None,
-                coil_values: Some(coils),
-            })
-        }
-        Some(FunctionCode::WriteMultipleRegisters) => {
-            let quantity = u16::from_be_bytes([frame[4], frame[5]]);
-            let byte_count = frame[6] as usize;
-
-            if frame.len() < 9 + byte_count {
-                return Err("Incomplete write multiple registers request".to_string());
-            }
-
-            let mut values = Vec::with_capacity(quantity as usize);
-            for i in 0..quantity as usize {
-                let idx = 7 + i * 2;
-                let value = u16::from_be_bytes([frame[idx], frame[idx + 1]]);
-                values.push(value);
-            }
+    parse_request_pdu(frame[0], &frame[1..frame.len() - 2])
+}
 
-            Ok(ParsedRtuRequest {
-                slave_id,
-                function_code: fc,
-                start_address,
-                quantity,
-                write_values: Some(values),
-                coil_values: None,
-            })
-        }
-        None => Err(format!("Unknown function code: 0x{:02X}", fc)),
-    }
+/// Parse incoming Modbus ASCII request frame (':' .. LRC CRLF)
+pub fn parse_ascii_request(frame: &[u8]) -> Result<ParsedRtuRequest, String> {
+    parse_rtu_request(&ascii_to_rtu_frame(frame)?)
 }
 
 /// Parse incoming TCP request frame (MBAP header + PDU)
 pub fn parse_tcp_request(frame: &[u8]) -> Result<(u16, ParsedRtuRequest), String> {
-    if frame.len() < 12 {
+    if frame.len() < 8 {
         return Err("TCP frame too short".to_string());
     }
 
@@ -523,113 +1081,109 @@ pub fn parse_tcp_request(frame: &[u8]) -> Result<(u16, ParsedRtuRequest), String
     let _protocol_id = u16::from_be_bytes([frame[2], frame[3]]);
     let length = u16::from_be_bytes([frame[4], frame[5]]) as usize;
     let unit_id = frame[6];
-    let fc = frame[7];
-    let start_address = u16::from_be_bytes([frame[8], frame[9]]);
 
-    if frame.len() < 6 + length {
+    if length < 2 || frame.len() < 6 + length {
         return Err("Incomplete TCP frame".to_string());
     }
 
+    let request = parse_request_pdu(unit_id, &frame[7..6 + length])?;
+    Ok((transaction_id, request))
+}
+
+/// Parse a request PDU (function code + data).
+/// Unknown function codes parse successfully so they can be answered with exception 0x01.
+pub fn parse_request_pdu(slave_id: u8, pdu: &[u8]) -> Result<ParsedRtuRequest, String> {
+    let fc = *pdu.first().ok_or("Empty PDU")?;
+    let data = &pdu[1..];
+    let word = |idx: usize| -> Result<u16, String> {
+        data.get(idx..idx + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| format!("Incomplete request for FC 0x{:02X}", fc))
+    };
+
+    let mut request = ParsedRtuRequest {
+        slave_id,
+        function_code: fc,
+        start_address: 0,
+        quantity: 0,
+        write_values: None,
+        coil_values: None,
+        write_address: None,
+        pdu_data: data.to_vec(),
+    };
+
     match FunctionCode::from_u8(fc) {
         Some(FunctionCode::ReadCoils)
         | Some(FunctionCode::ReadDiscreteInputs)
         | Some(FunctionCode::ReadHoldingRegisters)
         | Some(FunctionCode::ReadInputRegisters) => {
-            let quantity = u16::from_be_bytes([frame[10], frame[11]]);
-            Ok((
-                transaction_id,
-                ParsedRtuRequest {
-                    slave_id: unit_id,
-                    function_code: fc,
-                    start_address,
-                    quantity,
-                    write_values: None,
-                    coil_values: None,
-                },
-            ))
+            request.start_address = word(0)?;
+            request.quantity = word(2)?;
         }
         Some(FunctionCode::WriteSingleCoil) => {
-            let value = u16::from_be_bytes([frame[10], frame[11]]);
-            let coil = value == 0xFF00;
-            Ok((
-                transaction_id,
-                ParsedRtuRequest {
-                    slave_id: unit_id,
-                    function_code: fc,
-                    start_address,
-                    quantity: 1,
-                    write_values: None,
-                    coil_values: Some(vec![coil]),
-                },
-            ))
+            request.start_address = word(0)?;
+            request.quantity = 1;
+            request.coil_values = Some(vec![word(2)? == 0xFF00]);
         }
         Some(FunctionCode::WriteSingleRegister) => {
-            let value = u16::from_be_bytes([frame[10], frame[11]]);
-            Ok((
-                transaction_id,
-                ParsedRtuRequest {
-                    slave_id: unit_id,
-                    function_code: fc,
-                    start_address,
-                    quantity: 1,
-                    write_values: Some(vec![value]),
-                    coil_values: None,
-                },
-            ))
+            request.start_address = word(0)?;
+            request.quantity = 1;
+            request.write_values = Some(vec![word(2)?]);
         }
         Some(FunctionCode::WriteMultipleCoils) => {
-            let quantity = u16::from_be_bytes([frame[10], frame[11]]);
-            let _byte_count = frame[12] as usize;
-
-            let mut coils = Vec::with_capacity(quantity as usize);
-            for i in 0..quantity as usize {
-                let byte_idx = 13 + (i / 8);
-                let bit_idx = i % 8;
-                if byte_idx < frame.len() {
-                    let coil = (frame[byte_idx] >> bit_idx) & 1 == 1;
-                    coils.push(coil);
-                }
-            }
-
-            Ok((
-                transaction_id,
-                ParsedRtuRequest {
-                    slave_id: unit_id,
-                    function_code: fc,
-                    start_address,
-                    quantity,
-                    write_values: None,
-                    coil_values: Some(coils),
-                },
-            ))
+            request.start_address = word(0)?;
+            request.quantity = word(2)?;
+            let byte_count = *data.get(4).ok_or("Incomplete write multiple coils request")? as usize;
+            let bytes = data
+                .get(5..5 + byte_count)
+                .ok_or("Incomplete write multiple coils request")?;
+
+            let coils = (0..request.quantity as usize)
+                .filter(|i| i / 8 < bytes.len())
+                .map(|i| (bytes[i / 8] >> (i % 8)) & 1 == 1)
+                .collect();
+            request.coil_values = Some(coils);
         }
         Some(FunctionCode::WriteMultipleRegisters) => {
-            let quantity = u16::from_be_bytes([frame[10], frame[11]]);
-            let _byte_count = frame[12] as usize;
-
-            let mut values = Vec::with_capacity(quantity as usize);
-            for i in 0..quantity as usize {
-                let idx = 13 + i * 2;
-                if idx + 1 < frame.len() {
-                    let value = u16::from_be_bytes([frame[idx], frame[idx + 1]]);
-                    values.push(value);
-                }
+            request.start_address = word(0)?;
+            request.quantity = word(2)?;
+            let byte_count = *data.get(4).ok_or("Incomplete write multiple registers request")? as usize;
+            if data.len() < 5 + byte_count {
+                return Err("Incomplete write multiple registers request".to_string());
             }
 
-            Ok((
-                transaction_id,
-                ParsedRtuRequest {
-                    slave_id: unit_id,
-                    function_code: fc,
-                    start_address,
-                    quantity,
-                    write_values: Some(values),
-                    coil_values: None,
-                },
-            ))
+            let values = (0..request.quantity as usize)
+                .map(|i| word(5 + i * 2))
+                .collect::<Result<Vec<_>, _>>()?;
+            request.write_values = Some(values);
+        }
+        Some(FunctionCode::MaskWriteRegister) => {
+            request.start_address = word(0)?;
+            request.quantity = 1;
+            request.write_values = Some(vec![word(2)?, word(4)?]); // AND mask, OR mask
+        }
+        Some(FunctionCode::ReadWriteMultipleRegisters) => {
+            request.start_address = word(0)?;
+            request.quantity = word(2)?;
+            request.write_address = Some(word(4)?);
+            let write_quantity = word(6)? as usize;
+            let values = (0..write_quantity)
+                .map(|i| word(9 + i * 2))
+                .collect::<Result<Vec<_>, _>>()?;
+            request.write_values = Some(values);
+        }
+        Some(FunctionCode::Diagnostics) => {
+            request.start_address = word(0)?; // sub-function
+        }
+        Some(FunctionCode::ReadDeviceIdentification) => {
+            if data.len() < 3 {
+                return Err("Incomplete read device identification request".to_string());
+            }
         }
-        None => Err(format!("Unknown function code: 0x{:02X}", fc)),
+        Some(FunctionCode::ReadExceptionStatus) | Some(FunctionCode::ReportServerId) | None => {}
     }
+
+    Ok(request)
 }
 
 /// Process a Modbus request and generate response
@@ -649,12 +1203,19 @@ pub fn process_request(
     let data_type = match FunctionCode::from_u8(fc) {
         Some(FunctionCode::ReadCoils) | Some(FunctionCode::WriteSingleCoil) | Some(FunctionCode::WriteMultipleCoils) => "coil",
         Some(FunctionCode::ReadDiscreteInputs) => "discrete_input",
-        Some(FunctionCode::ReadHoldingRegisters) | Some(FunctionCode::WriteSingleRegister) | Some(FunctionCode::WriteMultipleRegisters) => "holding_register",
+        Some(FunctionCode::ReadHoldingRegisters)
+        | Some(FunctionCode::WriteSingleRegister)
+        | Some(FunctionCode::WriteMultipleRegisters)
+        | Some(FunctionCode::MaskWriteRegister)
+        | Some(FunctionCode::ReadWriteMultipleRegisters) => "holding_register",
         Some(FunctionCode::ReadInputRegisters) => "input_register",
-        None => "",
+        _ => "",
     };
 
-    if let Some(exception_code) = handle.get_exception(data_type, addr) {
+    let exception = handle
+        .unit(Some(request.slave_id))
+        .and_then(|unit| unit.get_exception(data_type, addr));
+    if let Some(exception_code) = exception {
         let response = build_exception_response(request.slave_id, fc, exception_code, mode, transaction_id);
         return ProcessedRequest {
             response_frame: response,
@@ -666,9 +1227,12 @@ pub fn process_request(
         };
     }
 
-    // Validate address range
+    // Validate address range (FC23 also checks the write range)
     let end_addr = addr as usize + qty as usize;
-    if end_addr > DEFAULT_DATA_SIZE {
+    let write_end_addr = request.write_address.map_or(0, |a| {
+        a as usize + request.write_values.as_ref().map_or(0, |v| v.len())
+    });
+    if end_addr > DEFAULT_DATA_SIZE || write_end_addr > DEFAULT_DATA_SIZE {
         let response = build_exception_response(request.slave_id, fc, 0x02, mode, transaction_id);
         return ProcessedRequest {
             response_frame: response,
@@ -743,6 +1307,7 @@ pub fn process_request(
                     let response = build_write_single_coil_response(request.slave_id, addr, value, mode, transaction_id);
                     let changed_event = ModbusSlaveDataChangedEvent {
                         connection_id: connection_id.to_string(),
+                        unit_id: request.slave_id,
                         data_type: "coil".to_string(),
                         start_address: addr,
                         values: vec![if value { 1 } else { 0 }],
@@ -778,6 +1343,7 @@ pub fn process_request(
                     let response = build_write_single_register_response(request.slave_id, addr, value, mode, transaction_id);
                     let changed_event = ModbusSlaveDataChangedEvent {
                         connection_id: connection_id.to_string(),
+                        unit_id: request.slave_id,
                         data_type: "holding_register".to_string(),
                         start_address: addr,
                         values: vec![value],
@@ -814,6 +1380,7 @@ pub fn process_request(
                 let response = build_write_multiple_response(request.slave_id, fc, addr, qty, mode, transaction_id);
                 let changed_event = ModbusSlaveDataChangedEvent {
                     connection_id: connection_id.to_string(),
+                    unit_id: request.slave_id,
                     data_type: "coil".to_string(),
                     start_address: addr,
                     values: coil_values.iter().map(|&c| if c { 1 } else { 0 }).collect(),
@@ -849,6 +1416,7 @@ pub fn process_request(
                 let response = build_write_multiple_response(request.slave_id, fc, addr, qty, mode, transaction_id);
                 let changed_event = ModbusSlaveDataChangedEvent {
                     connection_id: connection_id.to_string(),
+                    unit_id: request.slave_id,
                     data_type: "holding_register".to_string(),
                     start_address: addr,
                     values: write_values.clone(),
@@ -873,7 +1441,124 @@ pub fn process_request(
                 quantity: qty,
             }
         }
-        None => {
+        Some(FunctionCode::MaskWriteRegister) => {
+            let Some([and_mask, or_mask]) = request.write_values.as_deref().and_then(|v| <[u16; 2]>::try_from(v).ok()) else {
+                let response = build_exception_response(request.slave_id, fc, 0x03, mode, transaction_id);
+                return ProcessedRequest {
+                    response_frame: response,
+                    success: false,
+                    error_message: Some("Illegal Data Value".to_string()),
+                    data_changed: None,
+                    start_address: addr,
+                    quantity: qty,
+                };
+            };
+
+            let value = {
+                let mut registers = data.holding_registers.write();
+                let current = registers[addr as usize];
+                let value = (current & and_mask) | (or_mask & !and_mask);
+                registers[addr as usize] = value;
+                value
+            };
+
+            // Response echoes the request
+            let mut pdu = Vec::with_capacity(6);
+            pdu.extend_from_slice(&addr.to_be_bytes());
+            pdu.extend_from_slice(&and_mask.to_be_bytes());
+            pdu.extend_from_slice(&or_mask.to_be_bytes());
+            let response = build_response_frame(request.slave_id, fc, &pdu, mode, transaction_id);
+
+            ProcessedRequest {
+                response_frame: response,
+                success: true,
+                error_message: None,
+                data_changed: Some(ModbusSlaveDataChangedEvent {
+                    connection_id: connection_id.to_string(),
+                    unit_id: request.slave_id,
+                    data_type: "holding_register".to_string(),
+                    start_address: addr,
+                    values: vec![value],
+                    timestamp: get_timestamp(),
+                }),
+                start_address: addr,
+                quantity: 1,
+            }
+        }
+        Some(FunctionCode::ReadWriteMultipleRegisters) => {
+            let write_values = request.write_values.as_deref().unwrap_or_default();
+            let write_addr = request.write_address.unwrap_or(0);
+            if qty == 0 || qty > 125 || write_values.is_empty() || write_values.len() > 121 {
+                let response = build_exception_response(request.slave_id, fc, 0x03, mode, transaction_id);
+                return ProcessedRequest {
+                    response_frame: response,
+                    success: false,
+                    error_message: Some("Illegal Data Value".to_string()),
+                    data_changed: None,
+                    start_address: addr,
+                    quantity: qty,
+                };
+            }
+
+            // Write is performed before the read
+            let values: Vec<u16> = {
+                let mut registers = data.holding_registers.write();
+                registers[write_addr as usize..write_addr as usize + write_values.len()]
+                    .copy_from_slice(write_values);
+                registers[addr as usize..end_addr].to_vec()
+            };
+
+            let response = build_read_registers_response(request.slave_id, fc, &values, mode, transaction_id);
+            ProcessedRequest {
+                response_frame: response,
+                success: true,
+                error_message: None,
+                data_changed: Some(ModbusSlaveDataChangedEvent {
+                    connection_id: connection_id.to_string(),
+                    unit_id: request.slave_id,
+                    data_type: "holding_register".to_string(),
+                    start_address: write_addr,
+                    values: write_values.to_vec(),
+                    timestamp: get_timestamp(),
+                }),
+                start_address: addr,
+                quantity: qty,
+            }
+        }
+        // Sub-function 0x0000 (Return Query Data): echo the request
+        Some(FunctionCode::Diagnostics) if addr == 0x0000 => {
+            let response = build_response_frame(request.slave_id, fc, &request.pdu_data, mode, transaction_id);
+            ProcessedRequest {
+                response_frame: response,
+                success: true,
+                error_message: None,
+                data_changed: None,
+                start_address: addr,
+                quantity: qty,
+            }
+        }
+        Some(FunctionCode::ReadDeviceIdentification) if request.pdu_data.first() == Some(&0x0E) => {
+            let objects = handle.device_identification.read();
+            match build_device_identification_data(&objects, request.pdu_data[1], request.pdu_data[2]) {
+                Ok(pdu) => ProcessedRequest {
+                    response_frame: build_response_frame(request.slave_id, fc, &pdu, mode, transaction_id),
+                    success: true,
+                    error_message: None,
+                    data_changed: None,
+                    start_address: addr,
+                    quantity: qty,
+                },
+                Err(exception_code) => ProcessedRequest {
+                    response_frame: build_exception_response(request.slave_id, fc, exception_code, mode, transaction_id),
+                    success: false,
+                    error_message: Some(format_exception_error(exception_code)),
+                    data_changed: None,
+                    start_address: addr,
+                    quantity: qty,
+                },
+            }
+        }
+        _ => {
             let response = build_exception_response(request.slave_id, fc, 0x01, mode, transaction_id);
             ProcessedRequest {
                 response_frame: response,
@@ -903,6 +1588,22 @@ fn build_exception_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, exception_fc, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, exception_fc, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, exception_fc, &data),
+    }
+}
+
+/// Build a response frame from raw PDU data
+fn build_response_frame(
+    slave_id: u8,
+    fc: u8,
+    data: &[u8],
+    mode: ModbusMode,
+    transaction_id: u16,
+) -> Vec<u8> {
+    match mode {
+        ModbusMode::Rtu => build_rtu_frame(slave_id, fc, data),
+        ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, fc, data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, fc, data),
     }
 }
 
@@ -932,6 +1633,7 @@ fn build_read_coils_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, fc, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, fc, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, fc, &data),
     }
 }
 
@@ -953,6 +1655,7 @@ fn build_read_registers_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, fc, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, fc, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, fc, &data),
     }
 }
 
@@ -971,6 +1674,7 @@ fn build_write_single_coil_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, 0x05, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, 0x05, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, 0x05, &data),
     }
 }
 
@@ -989,6 +1693,7 @@ fn build_write_single_register_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, 0x06, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, 0x06, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, 0x06, &data),
     }
 }
 
@@ -1008,6 +1713,7 @@ fn build_write_multiple_response(
     match mode {
         ModbusMode::Rtu => build_rtu_frame(slave_id, fc, &data),
         ModbusMode::Tcp => build_tcp_frame(transaction_id, slave_id, fc, &data),
+        ModbusMode::Ascii => build_ascii_frame(slave_id, fc, &data),
     }
 }
 
@@ -1040,6 +1746,44 @@ impl ModbusSlaveData {
         *self.holding_registers.write() = data.holding_registers;
         *self.input_registers.write() = data.input_registers;
     }
+
+    /// Store a value by data type name (bits are set when value != 0).
+    /// Returns the stored value if it changed.
+    pub fn set_value(&self, data_type: &str, address: u16, value: u16) -> Option<u16> {
+        let idx = address as usize;
+        match data_type {
+            "coil" | "discrete_input" => {
+                let mut bits = if data_type == "coil" {
+                    self.coils.write()
+                } else {
+                    self.discrete_inputs.write()
+                };
+                let bit = value != 0;
+                match bits.get_mut(idx) {
+                    Some(slot) if *slot != bit => {
+                        *slot = bit;
+                        Some(bit as u16)
+                    }
+                    _ => None,
+                }
+            }
+            "holding_register" | "input_register" => {
+                let mut registers = if data_type == "holding_register" {
+                    self.holding_registers.write()
+                } else {
+                    self.input_registers.write()
+                };
+                match registers.get_mut(idx) {
+                    Some(slot) if *slot != value => {
+                        *slot = value;
+                        Some(value)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -1068,6 +1812,156 @@ mod tests {
         assert_eq!(response[2], 6); // Byte count
     }
 
+    #[test]
+    fn test_parse_extended_requests() {
+        // Report Server ID: no data, still parsed so it can get exception 0x01
+        let frame = build_rtu_frame(1, 0x11, &[]);
+        let req = parse_rtu_request(&frame).unwrap();
+        assert_eq!(req.function_code, 0x11);
+
+        // Read/Write Multiple Registers: read 6 @ 3, write 3 @ 14
+        let pdu = [
+            0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF, 0x00, 0xFF, 0x00, 0xFF,
+        ];
+        let req = parse_request_pdu(1, &pdu).unwrap();
+        assert_eq!(req.start_address, 3);
+        assert_eq!(req.quantity, 6);
+        assert_eq!(req.write_address, Some(14));
+        assert_eq!(req.write_values, Some(vec![0xFF, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn test_process_extended_requests() {
+        let handle = ModbusSlaveHandle::new_tcp(ModbusSlaveTcpConfig {
+            listen_port: 502,
+            bind_address: "127.0.0.1".to_string(),
+            unit_id: 1,
+            rtu_over_tcp: false,
+            extra_unit_ids: Vec::new(),
+        });
+        let data = handle.data.clone();
+        let process = |frame: &[u8]| {
+            let request = parse_rtu_request(frame).unwrap();
+            process_request(&request, &data, ModbusMode::Rtu, 0, "test", &handle).response_frame
+        };
+
+        // Mask Write: (0x12 & 0xF2) | (0x25 & !0xF2) = 0x17, echoed back
+        data.holding_registers.write()[4] = 0x0012;
+        let mask = build_rtu_frame(1, 0x16, &[0x00, 0x04, 0x00, 0xF2, 0x00, 0x25]);
+        assert_eq!(process(&mask), mask);
+        assert_eq!(data.holding_registers.read()[4], 0x0017);
+
+        // Read/Write Multiple: the write (2 @ 14) happens before the read (2 @ 14)
+        let read_write = build_rtu_frame(
+            1,
+            0x17,
+            &[0x00, 0x0E, 0x00, 0x02, 0x00, 0x0E, 0x00, 0x02, 0x04, 0x00, 0xAA, 0x00, 0xBB],
+        );
+        assert_eq!(process(&read_write), build_rtu_frame(1, 0x17, &[0x04, 0x00, 0xAA, 0x00, 0xBB]));
+
+        // Diagnostics sub-function 0 (return query data) echoes the request
+        let echo = build_rtu_frame(1, 0x08, &[0x00, 0x00, 0x12, 0x34]);
+        assert_eq!(process(&echo), echo);
+
+        // Unsupported function code: exception 0x01
+        let report = build_rtu_frame(1, 0x11, &[]);
+        assert_eq!(process(&report), build_exception_response(1, 0x11, 0x01, ModbusMode::Rtu, 0));
+    }
+
+    #[test]
+    fn test_device_identification_data() {
+        let objects = default_device_identification();
+        let data = build_device_identification_data(&objects, 0x01, 0x00).unwrap();
+        assert_eq!(&data[..6], &[0x0E, 0x01, 0x81, 0x00, 0x00, 0x03]);
+        assert_eq!(&data[6..16], &[0x00, 0x08, b'T', b'e', b'r', b'm', b'i', b'P', b'r', b'o']);
+
+        // Individual access to a missing object
+        assert_eq!(build_device_identification_data(&objects, 0x04, 0x05), Err(0x02));
+        assert_eq!(build_device_identification_data(&objects, 0x07, 0x00), Err(0x03));
+    }
+
+    #[test]
+    fn test_multi_unit_tcp_slave() {
+        let handle = ModbusSlaveHandle::new_tcp(ModbusSlaveTcpConfig {
+            listen_port: 502,
+            bind_address: "127.0.0.1".to_string(),
+            unit_id: 1,
+            rtu_over_tcp: false,
+            extra_unit_ids: vec![2],
+        });
+        assert_eq!(handle.unit_ids(), vec![1, 2]);
+
+        // Write HR 5 = 0x1234 on unit 2 only
+        let frame = build_tcp_frame(9, 2, 0x06, &[0x00, 0x05, 0x12, 0x34]);
+        let (transaction_id, request) = parse_tcp_request(&frame).unwrap();
+        let unit = handle.unit(Some(2)).unwrap();
+        let result = process_request(&request, &unit.data, ModbusMode::Tcp, transaction_id, "test", &handle);
+        assert!(result.success);
+        assert_eq!(unit.data.holding_registers.read()[5], 0x1234);
+        assert_eq!(handle.data.holding_registers.read()[5], 0);
+
+        // Unknown unit: exception 0x0B with the request's transaction ID
+        let frame = build_tcp_frame(10, 7, 0x03, &[0x00, 0x00, 0x00, 0x01]);
+        let (transaction_id, request) = parse_tcp_request(&frame).unwrap();
+        assert!(handle.unit(Some(7)).is_none());
+        let result = ProcessedRequest::exception(&request, 0x0B, ModbusMode::Tcp, transaction_id);
+        assert_eq!(result.response_frame, build_tcp_frame(10, 7, 0x83, &[0x0B]));
+
+        // Simulations run on their own unit
+        handle.simulations.write().push(SimulationConfig {
+            unit_id: Some(2),
+            data_type: "holding_register".to_string(),
+            address: 8,
+            simulation: SimulationType::Ramp {
+                min: 10,
+                max: 20,
+                step: 1,
+                interval_ms: 100,
+                reverse_at_bounds: false,
+            },
+        });
+        let mut runtimes = HashMap::new();
+        let events = handle.run_simulations(&mut runtimes, "test", 1000);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].unit_id, 2);
+        assert_eq!(unit.data.holding_registers.read()[8], 10);
+        assert_eq!(handle.data.holding_registers.read()[8], 0);
+    }
+
+    #[test]
+    fn test_multi_drop_rtu_slave() {
+        let handle = ModbusSlaveHandle::new_rtu(ModbusSlaveRtuConfig {
+            port_name: "/dev/ttyUSB0".to_string(),
+            baud_rate: 9600,
+            data_bits: 8,
+            stop_bits: "1".to_string(),
+            parity: "none".to_string(),
+            slave_id: 1,
+            flow_control: "none".to_string(),
+            mode: ModbusMode::Rtu,
+            extra_slave_ids: vec![0, 5, 6],
+            broadcast: true,
+        });
+        // ID 0 is never a unit: it is the broadcast address
+        assert_eq!(handle.unit_ids(), vec![1, 5, 6]);
+        assert!(handle.unit(Some(9)).is_none());
+        assert!(handle.add_unit(0).is_err());
+        assert!(handle.broadcast_enabled());
+
+        let request = parse_rtu_request(&build_rtu_frame(0, 0x06, &[0x00, 0x02, 0x00, 0x07])).unwrap();
+        assert!(is_broadcast_function(request.function_code));
+        assert!(!is_broadcast_function(0x03));
+
+        // FC06 to ID 0 writes HR 2 = 7 on every unit
+        let events = handle.apply_broadcast(&request, "test");
+        let units: Vec<u8> = events.iter().map(|e| e.unit_id).collect();
+        assert_eq!(units, vec![1, 5, 6]);
+        for id in [1, 5, 6] {
+            assert_eq!(handle.unit(Some(id)).unwrap().data.holding_registers.read()[2], 7);
+        }
+        assert_eq!(handle.statistics.read().total_requests, 1);
+    }
+
     #[test]
     fn test_statistics() {
         let mut stats = SlaveStatistics::default();
@@ -1083,4 +1977,115 @@ mod tests {
         assert_eq!(stats.response_time_max_ms, 15);
         assert!((stats.average_response_time_ms() - 10.0).abs() < 0.01);
     }
+
+    #[test]
+    fn test_ramp_simulation_reverses_at_bounds() {
+        let sim = SimulationType::Ramp {
+            min: 0,
+            max: 10,
+            step: 4,
+            interval_ms: 100,
+            reverse_at_bounds: true,
+        };
+        let mut runtime = SimulationRuntime::default();
+        let values: Vec<u16> = (1..=6)
+            .filter_map(|i| sim.next_value(&mut runtime, 1000 + i * 100))
+            .collect();
+        assert_eq!(values, vec![0, 4, 8, 10, 6, 2]);
+
+        // Not due yet
+        assert_eq!(sim.next_value(&mut runtime, 1650), None);
+    }
+
+    #[test]
+    fn test_sine_simulation_stays_in_range() {
+        let sim = SimulationType::SinWave { min: 100, max: 200, period_ms: 1000 };
+        let mut runtime = SimulationRuntime::default();
+        for i in 1..=40 {
+            if let Some(v) = sim.next_value(&mut runtime, 1000 + i * SIMULATION_TICK_MS) {
+                assert!((100..=200).contains(&v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_value_reports_changes() {
+        let data = ModbusSlaveData::default();
+        assert_eq!(data.set_value("holding_register", 5, 42), Some(42));
+        assert_eq!(data.set_value("holding_register", 5, 42), None);
+        assert_eq!(data.set_value("coil", 3, 7), Some(1));
+        assert!(data.coils.read()[3]);
+        assert_eq!(data.set_value("unknown", 0, 1), None);
+    }
+
+    #[test]
+    fn test_slave_rules_handshake() {
+        let handle = ModbusSlaveHandle::new_rtu(ModbusSlaveRtuConfig {
+            port_name: "/dev/ttyUSB0".to_string(),
+            baud_rate: 9600,
+            data_bits: 8,
+            stop_bits: "1".to_string(),
+            parity: "none".to_string(),
+            slave_id: 1,
+            flow_control: "none".to_string(),
+            mode: ModbusMode::Rtu,
+            extra_slave_ids: Vec::new(),
+            broadcast: false,
+        });
+        let unit = handle.primary_unit();
+        *unit.rules.write() = vec![
+            // Command HR 100 = 1 (start) sets the running coil 5
+            SlaveRule::OnWrite {
+                data_type: "holding_register".to_string(),
+                address: 100,
+                equals: Some(1),
+                target_type: "coil".to_string(),
+                target_address: 5,
+                set_value: 1,
+            },
+            SlaveRule::AutoClear {
+                data_type: "holding_register".to_string(),
+                address: 100,
+                delay_ms: 500,
+                reset_value: 0,
+            },
+            // Coil 5 is mirrored into discrete input 5 (chained rule)
+            SlaveRule::Mirror {
+                data_type: "coil".to_string(),
+                start_address: 0,
+                end_address: 9,
+                target_type: "discrete_input".to_string(),
+                target_start_address: 0,
+            },
+        ];
+
+        // Master writes HR 100 = 1
+        let write = parse_rtu_request(&build_rtu_frame(0x01, 0x06, &[0x00, 0x64, 0x00, 0x01])).unwrap();
+        let (result, rule_changes) = unit.process(&write, ModbusMode::Rtu, 0, "test", &handle);
+        assert!(result.success);
+
+        let changed: Vec<_> = rule_changes.iter().map(|c| (c.data_type.as_str(), c.start_address)).collect();
+        assert_eq!(changed, vec![("coil", 5), ("discrete_input", 5)]);
+        assert!(unit.data.discrete_inputs.read()[5]);
+
+        // Rewriting the same value restarts the delay: the first clear is superseded
+        std::thread::sleep(Duration::from_millis(20));
+        let first_due = Instant::now() + Duration::from_millis(490);
+        unit.process(&write, ModbusMode::Rtu, 0, "test", &handle);
+        assert!(handle.run_auto_clears("test", first_due).is_empty());
+        assert_eq!(unit.data.holding_registers.read()[100], 1);
+
+        let cleared = handle.run_auto_clears("test", Instant::now() + Duration::from_millis(600));
+        assert_eq!(cleared.len(), 1);
+        assert_eq!((cleared[0].start_address, cleared[0].values.clone()), (100, vec![0]));
+        assert_eq!(unit.data.holding_registers.read()[100], 0);
+
+        assert!(validate_rule(&SlaveRule::AutoClear {
+            data_type: "holding_register".to_string(),
+            address: 100,
+            delay_ms: 0,
+            reset_value: 0,
+        })
+        .is_err());
+    }
 }
@@ -0,0 +1,331 @@
+// Modbus Sniffer Module for TermiPro
+// Passive RTU bus monitor: frames are split by the 3.5-character gap, decoded and paired
+// (request -> response) without ever writing to the bus
+
+use crate::modbus::{
+    calculate_expected_response_length, default_flow_control, get_timestamp, parse_rtu_response,
+    take_rtu_frame, verify_crc16, FunctionCode, ModbusMode,
+};
+use crate::modbus_slave::{is_broadcast_function, parse_rtu_request, ParsedRtuRequest};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// ===================== CONFIG STRUCTS =====================
+
+/// Sniffer configuration: serial parameters of the monitored bus
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModbusSniffConfig {
+    pub port_name: String,
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: String,
+    pub parity: String,
+    #[serde(default = "default_flow_control")]
+    pub flow_control: String,
+    /// A request with no answer within this time is no longer paired
+    #[serde(default = "default_sniff_response_timeout")]
+    pub response_timeout_ms: u32,
+}
+
+fn default_sniff_response_timeout() -> u32 {
+    1000
+}
+
+// ===================== EVENT STRUCTS =====================
+
+/// One frame seen on the bus
+#[derive(Debug, Serialize, Clone)]
+pub struct ModbusSniffEvent {
+    pub sniffer_id: String,
+    /// "request", "response", "exception", "crc_error" or "unknown"
+    pub kind: String,
+    pub slave_id: u8,
+    pub function_code: u8,
+    pub start_address: Option<u16>,
+    pub quantity: Option<u16>,
+    pub values: Option<Vec<u16>>,
+    pub coils: Option<Vec<bool>>,
+    pub exception_code: Option<u8>,
+    pub error: Option<String>,
+    pub crc_ok: bool,
+    pub frame: Vec<u8>,
+    /// Bus silence before this frame (since the end of the previous frame)
+    pub gap_us: Option<u64>,
+    /// Responses only: end of the request to start of the response
+    pub response_time_ms: Option<u64>,
+    pub timestamp: u64,
+}
+
+// ===================== STATE =====================
+
+/// State for managing running sniffers (keyed by sniffer ID)
+#[derive(Default)]
+pub struct ModbusSnifferState {
+    pub running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+// ===================== FRAMER =====================
+
+/// Frame cut from the bus with the arrival times of its first and last bytes
+pub type SniffedFrame = (Vec<u8>, Instant, Instant);
+
+/// Rebuilds frames from the chunks delimited by the 3.5-character gap.
+/// USB serial adapters deliver bytes in bursts, so one chunk may hold several frames
+/// (split by their expected length and CRC) and a frame may be cut across two chunks
+/// (a chunk without a valid frame is joined with the next one before being reported as is).
+#[derive(Default)]
+pub struct SniffFramer {
+    buffer: Vec<u8>,
+    /// Arrival of the first byte held in `buffer`
+    started_at: Option<Instant>,
+}
+
+impl SniffFramer {
+    /// Add a gap-delimited chunk, returning the frames it completes in bus order
+    pub fn push(&mut self, chunk: &[u8], started_at: Instant, ended_at: Instant) -> Vec<SniffedFrame> {
+        let carried = self.buffer.len();
+        let first_start = self.started_at.unwrap_or(started_at);
+        self.buffer.extend_from_slice(chunk);
+
+        let mut frames = self.take_frames(first_start, started_at, ended_at);
+
+        // Bytes held from the previous chunk still do not form a frame: report them as they are
+        if carried > 0 && frames.is_empty() && self.buffer.len() >= carried {
+            let stale: Vec<u8> = self.buffer.drain(..carried).collect();
+            frames.push((stale, first_start, started_at));
+            frames.extend(self.take_frames(started_at, started_at, ended_at));
+        }
+
+        self.started_at = (!self.buffer.is_empty()).then_some(started_at);
+        frames
+    }
+
+    fn take_frames(&mut self, first_start: Instant, started_at: Instant, ended_at: Instant) -> Vec<SniffedFrame> {
+        let mut frames = Vec::new();
+        while let Some(frame) = take_rtu_frame(&mut self.buffer) {
+            let start = if frames.is_empty() { first_start } else { started_at };
+            frames.push((frame, start, ended_at));
+        }
+        frames
+    }
+}
+
+// ===================== DECODER =====================
+
+/// Decodes bus frames and pairs each response with the request that preceded it
+pub struct ModbusSniffDecoder {
+    sniffer_id: String,
+    response_timeout: Duration,
+    /// Last unanswered request and the time it ended
+    pending: Option<(ParsedRtuRequest, Instant)>,
+}
+
+impl ModbusSniffDecoder {
+    pub fn new(sniffer_id: String, response_timeout: Duration) -> Self {
+        Self {
+            sniffer_id,
+            response_timeout,
+            pending: None,
+        }
+    }
+
+    /// Decode one frame. `started_at` / `ended_at` are the arrival times of its first and last bytes.
+    pub fn decode(
+        &mut self,
+        frame: &[u8],
+        started_at: Instant,
+        ended_at: Instant,
+        gap_us: Option<u64>,
+    ) -> ModbusSniffEvent {
+        let mut event = ModbusSniffEvent {
+            sniffer_id: self.sniffer_id.clone(),
+            kind: "unknown".to_string(),
+            slave_id: frame.first().copied().unwrap_or(0),
+            function_code: frame.get(1).map(|fc| fc & 0x7F).unwrap_or(0),
+            start_address: None,
+            quantity: None,
+            values: None,
+            coils: None,
+            exception_code: None,
+            error: None,
+            crc_ok: frame.len() >= 4 && verify_crc16(frame),
+            frame: frame.to_vec(),
+            gap_us,
+            response_time_ms: None,
+            timestamp: get_timestamp(),
+        };
+
+        if !event.crc_ok {
+            event.kind = "crc_error".to_string();
+            event.error = Some(if frame.len() < 4 { "Frame too short" } else { "CRC error" }.to_string());
+            return event;
+        }
+
+        // A frame matching the pending request is tried as its response first
+        if let Some((request, request_end)) = self.pending.take() {
+            let expired = started_at.saturating_duration_since(request_end) > self.response_timeout;
+            if !expired && is_response_to(&request, frame) {
+                if let Ok(parsed) = parse_rtu_response(frame, request.function_code) {
+                    event.start_address = Some(request.start_address);
+                    event.quantity = Some(request.quantity);
+                    event.response_time_ms =
+                        Some(started_at.saturating_duration_since(request_end).as_millis() as u64);
+
+                    if parsed.is_exception {
+                        event.kind = "exception".to_string();
+                        event.exception_code = parsed.exception_code;
+                    } else {
+                        event.kind = "response".to_string();
+                        event.values = parsed.data;
+                        event.coils = parsed.coils.map(|mut coils| {
+                            coils.truncate(request.quantity as usize);
+                            coils
+                        });
+                    }
+                    return event;
+                }
+            }
+        }
+
+        match parse_rtu_request(frame) {
+            // An unpaired reply can parse as a request: the request layout must match exactly
+            Ok(_) if request_length(frame) != Some(frame.len()) => {
+                event.error = Some("Length does not match a request for this function code".to_string());
+            }
+            Ok(request) => {
+                event.kind = "request".to_string();
+                event.start_address = Some(request.start_address);
+                event.quantity = Some(request.quantity);
+                event.values = request.write_values.clone();
+                event.coils = request.coil_values.clone();
+
+                // Broadcast writes are never answered
+                if !(request.slave_id == 0 && is_broadcast_function(request.function_code)) {
+                    self.pending = Some((request, ended_at));
+                }
+            }
+            Err(e) => event.error = Some(e),
+        }
+
+        event
+    }
+}
+
+/// Expected RTU length of a request with this frame's function code (None if unknown)
+fn request_length(frame: &[u8]) -> Option<usize> {
+    let byte_at = |idx: usize| frame.get(idx).map(|&b| b as usize);
+    match FunctionCode::from_u8(frame[1])? {
+        FunctionCode::ReadExceptionStatus | FunctionCode::ReportServerId => Some(4),
+        FunctionCode::ReadDeviceIdentification => Some(7),
+        FunctionCode::WriteMultipleCoils | FunctionCode::WriteMultipleRegisters => byte_at(6).map(|n| 9 + n),
+        FunctionCode::MaskWriteRegister => Some(10),
+        FunctionCode::ReadWriteMultipleRegisters => byte_at(10).map(|n| 13 + n),
+        _ => Some(8),
+    }
+}
+
+/// Whether a frame has the slave, function code and length of a reply to `request`
+fn is_response_to(request: &ParsedRtuRequest, frame: &[u8]) -> bool {
+    if frame[0] != request.slave_id {
+        return false;
+    }
+    if frame[1] == request.function_code | 0x80 {
+        return frame.len() == 5;
+    }
+    if frame[1] != request.function_code {
+        return false;
+    }
+
+    // Read replies have a length fixed by the requested quantity
+    match FunctionCode::from_u8(request.function_code) {
+        Some(FunctionCode::ReadCoils)
+        | Some(FunctionCode::ReadDiscreteInputs)
+        | Some(FunctionCode::ReadHoldingRegisters)
+        | Some(FunctionCode::ReadInputRegisters)
+        | Some(FunctionCode::ReadWriteMultipleRegisters) => {
+            frame.len()
+                == calculate_expected_response_length(request.function_code, request.quantity, ModbusMode::Rtu)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modbus::build_rtu_frame;
+
+    #[test]
+    fn test_sniff_pairing() {
+        let mut decoder = ModbusSniffDecoder::new("sniff".to_string(), Duration::from_millis(1000));
+        let t0 = Instant::now();
+
+        // Read 2 holding registers at 100 from slave 1, then its reply
+        let request = build_rtu_frame(0x01, 0x03, &[0x00, 0x64, 0x00, 0x02]);
+        let event = decoder.decode(&request, t0, t0, None);
+        assert_eq!(event.kind, "request");
+        assert_eq!((event.start_address, event.quantity), (Some(100), Some(2)));
+
+        let reply = build_rtu_frame(0x01, 0x03, &[0x04, 0x00, 0x2A, 0x01, 0x00]);
+        let event = decoder.decode(&reply, t0 + Duration::from_millis(12), t0 + Duration::from_millis(14), Some(12_000));
+        assert_eq!(event.kind, "response");
+        assert_eq!(event.start_address, Some(100));
+        assert_eq!(event.values, Some(vec![42, 256]));
+        assert_eq!(event.response_time_ms, Some(12));
+
+        // Write single register echo pairs with the request, exception replies are decoded
+        let write = build_rtu_frame(0x02, 0x06, &[0x00, 0x0A, 0x12, 0x34]);
+        assert_eq!(decoder.decode(&write, t0, t0, None).kind, "request");
+        assert_eq!(decoder.decode(&write, t0, t0, None).kind, "response");
+
+        let read = build_rtu_frame(0x02, 0x04, &[0x00, 0x00, 0x00, 0x01]);
+        decoder.decode(&read, t0, t0, None);
+        let event = decoder.decode(&build_rtu_frame(0x02, 0x84, &[0x02]), t0, t0, None);
+        assert_eq!((event.kind.as_str(), event.exception_code), ("exception", Some(0x02)));
+
+        // Corrupted frame
+        let mut bad = request.clone();
+        bad[3] ^= 0xFF;
+        let event = decoder.decode(&bad, t0, t0, None);
+        assert_eq!(event.kind, "crc_error");
+        assert!(!event.crc_ok);
+
+        // A reply with no pending request is not mistaken for a request
+        let event = decoder.decode(&reply, t0, t0, None);
+        assert_eq!(event.kind, "unknown");
+    }
+
+    #[test]
+    fn test_sniff_framer_split_and_merged() {
+        let mut framer = SniffFramer::default();
+        let t0 = Instant::now();
+        let request = build_rtu_frame(0x01, 0x03, &[0x00, 0x64, 0x00, 0x02]);
+        let reply = build_rtu_frame(0x01, 0x03, &[0x04, 0x00, 0x2A, 0x01, 0x00]);
+
+        // Request cut by an adapter burst: held until the next chunk
+        assert!(framer.push(&request[..5], t0, t0).is_empty());
+        let frames = framer.push(&request[5..], t0, t0);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].0, request);
+
+        // Request and reply merged into one chunk are split and pair up
+        let merged = [request.clone(), reply.clone()].concat();
+        let frames = framer.push(&merged, t0, t0);
+        let frames: Vec<_> = frames.into_iter().map(|(frame, _, _)| frame).collect();
+        assert_eq!(frames, vec![request.clone(), reply.clone()]);
+
+        let mut decoder = ModbusSniffDecoder::new("sniff".to_string(), Duration::from_millis(1000));
+        let kinds: Vec<_> = frames.iter().map(|f| decoder.decode(f, t0, t0, None).kind).collect();
+        assert_eq!(kinds, vec!["request", "response"]);
+
+        // Noise is reported once the next chunk cannot complete it, without losing that chunk
+        assert!(framer.push(&[0x01, 0x03, 0x55], t0, t0).is_empty());
+        let frames = framer.push(&request, t0, t0);
+        let frames: Vec<_> = frames.into_iter().map(|(frame, _, _)| frame).collect();
+        assert_eq!(frames, vec![vec![0x01, 0x03, 0x55], request]);
+    }
+}
@@ -8,7 +8,8 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tauri::{AppHandle, Emitter};
+use crate::recorder::RecorderState;
+use tauri::{AppHandle, Emitter, Manager};
 
 // ===================== MQTT STRUCTS =====================
 
@@ -220,6 +221,13 @@ pub async fn run_eventloop(
                             timestamp: get_timestamp(),
                             direction: "rx".to_string(),
                         };
+                        app.state::<RecorderState>().record(
+                            &connection_id,
+                            "rx",
+                            &msg.payload,
+                            None,
+                            Some(&msg.topic),
+                        );
                         let _ = app.emit("mqtt-data", &msg);
                     }
                     Event::Incoming(Packet::ConnAck(connack)) => {
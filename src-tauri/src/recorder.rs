@@ -0,0 +1,532 @@
+// Session Recorder Module for TermiPro
+// Writes serial/TCP/MQTT traffic to disk as JSON Lines with file rotation,
+// and loads captures back for replay
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// ===================== STRUCTS =====================
+
+/// Recording configuration from frontend
+#[derive(Debug, Deserialize, Clone)]
+pub struct RecordingConfig {
+    /// Port name, TCP connection/server ID or MQTT connection ID
+    pub connection_id: String,
+    /// Base file path, e.g. "/data/soak.jsonl" -> soak_000.jsonl, soak_001.jsonl, ...
+    pub file_path: String,
+    /// Rotate when the current file exceeds this size (bytes)
+    #[serde(default)]
+    pub max_file_size_bytes: Option<u64>,
+    /// Rotate when the current file is older than this (seconds)
+    #[serde(default)]
+    pub rotate_interval_secs: Option<u64>,
+}
+
+/// One captured chunk (one JSON line in the recording file)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CaptureRecord {
+    pub timestamp: u64,
+    pub connection_id: String,
+    pub direction: String, // "tx" or "rx"
+    /// Raw bytes (MQTT exports call this "payload")
+    #[serde(alias = "payload")]
+    pub data: Vec<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+}
+
+/// Recording status returned to frontend
+#[derive(Debug, Serialize, Clone)]
+pub struct RecordingStatus {
+    pub connection_id: String,
+    pub current_file: String,
+    pub files: Vec<String>,
+    pub records_written: u64,
+    pub bytes_written: u64,
+    pub started_at: u64,
+    /// Write error that stopped the recording (records are no longer written)
+    pub error: Option<String>,
+}
+
+/// Replay configuration from frontend
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReplayConfig {
+    pub replay_id: String,
+    /// Recording file (JSON Lines) or JSON array export (mqtt_export_messages)
+    pub file_path: String,
+    /// "serial", "tcp_client" or "tcp_server"
+    pub target: String,
+    /// Port name, TCP connection ID or TCP server ID
+    pub target_id: String,
+    /// TCP server only: send to one client (None = all clients)
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Speed multiplier (2.0 = twice as fast)
+    #[serde(default = "default_speed")]
+    pub speed: f64,
+    #[serde(default)]
+    pub loop_playback: bool,
+    /// Only replay records captured in the TX direction
+    #[serde(default)]
+    pub tx_only: bool,
+}
+
+fn default_speed() -> f64 {
+    1.0
+}
+
+/// Replay status event
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplayStatus {
+    pub replay_id: String,
+    pub status: String, // "running", "finished", "stopped", "error"
+    pub sent: u64,
+    pub total: usize,
+    pub message: Option<String>,
+}
+
+/// One step of a replay: wait `delay`, then send `data`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayStep {
+    pub delay: Duration,
+    pub data: Vec<u8>,
+}
+
+/// Flush interval of the recording files
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Files and counters of a recording, updated by its writer thread
+#[derive(Debug, Default)]
+struct RecordingProgress {
+    files: Vec<String>,
+    records_written: u64,
+    bytes_written: u64,
+    error: Option<String>,
+}
+
+impl RecordingProgress {
+    fn status(&self, connection_id: &str, started_at: u64) -> RecordingStatus {
+        RecordingStatus {
+            connection_id: connection_id.to_string(),
+            current_file: self.files.last().cloned().unwrap_or_default(),
+            files: self.files.clone(),
+            records_written: self.records_written,
+            bytes_written: self.bytes_written,
+            started_at,
+            error: self.error.clone(),
+        }
+    }
+}
+
+/// Called by the writer thread with the final status when a write error stops a recording
+pub type RecordingErrorHandler = Box<dyn Fn(RecordingStatus) + Send>;
+
+/// Create a capture file, never overwriting the files of an earlier recording
+fn create_capture_file(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => format!("File {} already exists", path.display()),
+            _ => format!("Create file error: {}", e),
+        })
+}
+
+/// File side of a recording, owned by its writer thread
+struct RecordingWriter {
+    config: RecordingConfig,
+    writer: BufWriter<File>,
+    file_index: u32,
+    file_opened_at: Instant,
+    file_size: u64,
+    last_flush: Instant,
+    progress: Arc<Mutex<RecordingProgress>>,
+}
+
+impl RecordingWriter {
+    fn start(config: RecordingConfig, progress: Arc<Mutex<RecordingProgress>>) -> Result<Self, String> {
+        let path = rotated_path(&config.file_path, 0);
+        let file = create_capture_file(&path)?;
+        progress.lock().files.push(path.to_string_lossy().to_string());
+
+        Ok(Self {
+            config,
+            writer: BufWriter::new(file),
+            file_index: 0,
+            file_opened_at: Instant::now(),
+            file_size: 0,
+            last_flush: Instant::now(),
+            progress,
+        })
+    }
+
+    fn needs_rotation(&self) -> bool {
+        let size_exceeded = self
+            .config
+            .max_file_size_bytes
+            .is_some_and(|max| max > 0 && self.file_size >= max);
+        let time_exceeded = self
+            .config
+            .rotate_interval_secs
+            .is_some_and(|secs| secs > 0 && self.file_opened_at.elapsed() >= Duration::from_secs(secs));
+        size_exceeded || time_exceeded
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        self.flush()?;
+
+        self.file_index += 1;
+        let path = rotated_path(&self.config.file_path, self.file_index);
+        let file = create_capture_file(&path)?;
+
+        self.writer = BufWriter::new(file);
+        self.file_opened_at = Instant::now();
+        self.file_size = 0;
+        self.progress.lock().files.push(path.to_string_lossy().to_string());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.last_flush = Instant::now();
+        self.writer.flush().map_err(|e| format!("Write error: {}", e))
+    }
+
+    fn write(&mut self, record: &CaptureRecord) -> Result<(), String> {
+        if self.needs_rotation() {
+            self.rotate()?;
+        }
+
+        let mut line = serde_json::to_vec(record).map_err(|e| format!("Serialize error: {}", e))?;
+        line.push(b'\n');
+
+        self.writer
+            .write_all(&line)
+            .map_err(|e| format!("Write error: {}", e))?;
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+
+        self.file_size += line.len() as u64;
+        let mut progress = self.progress.lock();
+        progress.records_written += 1;
+        progress.bytes_written += record.data.len() as u64;
+        Ok(())
+    }
+
+    /// Write records until the session is stopped (sender dropped), flushing at least every
+    /// `FLUSH_INTERVAL`. A write error is kept in the progress, reported to `on_error`
+    /// and ends the thread, which stops the recording.
+    fn run(mut self, receiver: mpsc::Receiver<CaptureRecord>, started_at: u64, on_error: RecordingErrorHandler) {
+        loop {
+            let result = match receiver.recv_timeout(FLUSH_INTERVAL) {
+                Ok(record) => self.write(&record),
+                Err(mpsc::RecvTimeoutError::Timeout) => self.flush(),
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            if let Err(e) = result {
+                let status = {
+                    let mut progress = self.progress.lock();
+                    progress.error = Some(e);
+                    progress.status(&self.config.connection_id, started_at)
+                };
+                on_error(status);
+                return;
+            }
+        }
+        let _ = self.flush();
+    }
+}
+
+/// An active recording: records are handed to its writer thread, so no file I/O
+/// happens under the recorder lock
+struct RecordingSession {
+    connection_id: String,
+    sender: mpsc::Sender<CaptureRecord>,
+    writer: JoinHandle<()>,
+    progress: Arc<Mutex<RecordingProgress>>,
+    started_at: u64,
+}
+
+impl RecordingSession {
+    fn start(config: RecordingConfig, on_error: RecordingErrorHandler) -> Result<Self, String> {
+        let connection_id = config.connection_id.clone();
+        let progress = Arc::new(Mutex::new(RecordingProgress::default()));
+        let writer = RecordingWriter::start(config, progress.clone())?;
+        let (sender, receiver) = mpsc::channel();
+        let started_at = get_timestamp();
+
+        Ok(Self {
+            connection_id,
+            sender,
+            writer: thread::spawn(move || writer.run(receiver, started_at, on_error)),
+            progress,
+            started_at,
+        })
+    }
+
+    fn status(&self) -> RecordingStatus {
+        self.progress.lock().status(&self.connection_id, self.started_at)
+    }
+
+    /// Close the channel and wait for the writer thread to write the pending records
+    fn finish(self) -> RecordingStatus {
+        let Self { connection_id, sender, writer, progress, started_at } = self;
+        drop(sender);
+        let _ = writer.join();
+        let status = progress.lock().status(&connection_id, started_at);
+        status
+    }
+}
+
+/// State for managing all recordings (keyed by connection ID)
+#[derive(Default)]
+pub struct RecorderState {
+    sessions: Mutex<HashMap<String, RecordingSession>>,
+}
+
+impl RecorderState {
+    /// Start recording a connection. `on_error` receives the status if a write error stops it.
+    pub fn start(
+        &self,
+        config: RecordingConfig,
+        on_error: RecordingErrorHandler,
+    ) -> Result<RecordingStatus, String> {
+        let mut sessions = self.sessions.lock();
+        if sessions.contains_key(&config.connection_id) {
+            return Err(format!("Recording {} already running", config.connection_id));
+        }
+
+        let session = RecordingSession::start(config, on_error)?;
+        let status = session.status();
+        sessions.insert(status.connection_id.clone(), session);
+        Ok(status)
+    }
+
+    /// Stop recording a connection (waits for the pending records to be written)
+    pub fn stop(&self, connection_id: &str) -> Result<RecordingStatus, String> {
+        let session = self
+            .sessions
+            .lock()
+            .remove(connection_id)
+            .ok_or_else(|| format!("Recording {} not found", connection_id))?;
+
+        Ok(session.finish())
+    }
+
+    /// List active recordings
+    pub fn list(&self) -> Vec<RecordingStatus> {
+        self.sessions.lock().values().map(|s| s.status()).collect()
+    }
+
+    /// Record a chunk if the connection is being recorded
+    pub fn record(
+        &self,
+        connection_id: &str,
+        direction: &str,
+        data: &[u8],
+        client_id: Option<&str>,
+        topic: Option<&str>,
+    ) {
+        let mut sessions = self.sessions.lock();
+        let Some(session) = sessions.get_mut(connection_id) else {
+            return;
+        };
+
+        let record = CaptureRecord {
+            timestamp: get_timestamp(),
+            connection_id: connection_id.to_string(),
+            direction: direction.to_string(),
+            data: data.to_vec(),
+            client_id: client_id.map(str::to_string),
+            topic: topic.map(str::to_string),
+        };
+
+        // Fails once a write error has stopped the writer: the session stays listed
+        // with its error until recorder_stop
+        let _ = session.sender.send(record);
+    }
+}
+
+/// State for managing running replays (keyed by replay ID)
+#[derive(Default)]
+pub struct ReplayState {
+    pub running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+// ===================== HELPER FUNCTIONS =====================
+
+/// Get current timestamp in milliseconds
+fn get_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Build the file path for a rotation index: "dir/name.jsonl" -> "dir/name_003.jsonl"
+pub fn rotated_path(base: &str, index: u32) -> PathBuf {
+    let path = Path::new(base);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "capture".to_string());
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "jsonl".to_string());
+
+    path.with_file_name(format!("{}_{:03}.{}", stem, index, ext))
+}
+
+/// Load a capture file: JSON Lines (recorder) or a JSON array (MQTT export)
+pub fn load_capture(file_path: &str) -> Result<Vec<CaptureRecord>, String> {
+    let content =
+        std::fs::read_to_string(file_path).map_err(|e| format!("Read file error: {}", e))?;
+    parse_capture(&content)
+}
+
+/// Parse capture content, detecting the format from the first character
+pub fn parse_capture(content: &str) -> Result<Vec<CaptureRecord>, String> {
+    if content.trim_start().starts_with('[') {
+        return serde_json::from_str(content).map_err(|e| format!("Parse error: {}", e));
+    }
+
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|e| format!("Parse error at line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Build the replay steps, keeping the original inter-message timing divided by `speed`.
+/// Fails when a scaled gap is too long to represent (very small speed).
+pub fn build_replay_steps(
+    records: &[CaptureRecord],
+    speed: f64,
+    tx_only: bool,
+) -> Result<Vec<ReplayStep>, String> {
+    let mut steps = Vec::new();
+    let mut last_timestamp: Option<u64> = None;
+
+    for record in records {
+        if tx_only && record.direction != "tx" {
+            continue;
+        }
+
+        let gap_ms = last_timestamp
+            .map(|last| record.timestamp.saturating_sub(last))
+            .unwrap_or(0);
+        last_timestamp = Some(record.timestamp);
+
+        let delay = Duration::try_from_secs_f64(gap_ms as f64 / 1000.0 / speed)
+            .map_err(|_| format!("Replay delay out of range at speed {}", speed))?;
+        steps.push(ReplayStep {
+            delay,
+            data: record.data.clone(),
+        });
+    }
+
+    Ok(steps)
+}
+
+/// Shortest pause before a looped replay starts over
+pub const MIN_LOOP_DELAY: Duration = Duration::from_millis(1);
+
+/// Pause before the first step of each new loop iteration: the capture's first gap,
+/// at least `MIN_LOOP_DELAY` so a capture without gaps cannot flood the target
+pub fn loop_restart_delay(steps: &[ReplayStep]) -> Duration {
+    steps
+        .get(1)
+        .map(|step| step.delay)
+        .unwrap_or_default()
+        .max(MIN_LOOP_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotated_path() {
+        assert_eq!(
+            rotated_path("/tmp/soak.jsonl", 3),
+            PathBuf::from("/tmp/soak_003.jsonl")
+        );
+        assert_eq!(rotated_path("capture", 0), PathBuf::from("capture_000.jsonl"));
+    }
+
+    #[test]
+    fn test_record_rotates_by_size() {
+        let dir = std::env::temp_dir().join(format!("termipro-rec-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("cap.jsonl").to_string_lossy().to_string();
+
+        let state = RecorderState::default();
+        let config = RecordingConfig {
+            connection_id: "COM1".to_string(),
+            file_path: base,
+            max_file_size_bytes: Some(10),
+            rotate_interval_secs: None,
+        };
+        state.start(config.clone(), Box::new(|_| {})).unwrap();
+
+        state.record("COM1", "rx", b"hello", None, None);
+        state.record("COM1", "tx", b"world", None, None);
+        state.record("other", "rx", b"ignored", None, None);
+
+        let status = state.stop("COM1").unwrap();
+        assert_eq!(status.records_written, 2);
+        assert_eq!(status.bytes_written, 10);
+        assert_eq!(status.files.len(), 2);
+
+        let first = std::fs::read_to_string(&status.files[0]).unwrap();
+        let record: CaptureRecord = serde_json::from_str(first.trim()).unwrap();
+        assert_eq!(record.data, b"hello");
+        assert_eq!(record.direction, "rx");
+
+        // A new recording never overwrites the files of an earlier one
+        assert!(state.start(config, Box::new(|_| {})).is_err());
+        assert_eq!(std::fs::read_to_string(&status.files[0]).unwrap(), first);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_replay_steps_from_mqtt_export() {
+        let export = r#"[
+            {"connection_id":"m1","topic":"a","payload":[65],"qos":0,"retain":false,"timestamp":1000,"direction":"tx"},
+            {"connection_id":"m1","topic":"a","payload":[66],"qos":0,"retain":false,"timestamp":1500,"direction":"rx"},
+            {"connection_id":"m1","topic":"a","payload":[67],"qos":0,"retain":false,"timestamp":3000,"direction":"tx"}
+        ]"#;
+        let records = parse_capture(export).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let steps = build_replay_steps(&records, 2.0, true).unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].delay, Duration::ZERO);
+        assert_eq!(steps[1].delay, Duration::from_millis(1000));
+        assert_eq!(steps[1].data, b"C");
+        assert_eq!(loop_restart_delay(&steps), Duration::from_millis(1000));
+
+        let burst = build_replay_steps(&records[..1], 1.0, false).unwrap();
+        assert_eq!(loop_restart_delay(&burst), MIN_LOOP_DELAY);
+
+        // Gaps scaled past the Duration range are an error, not a panic
+        assert!(build_replay_steps(&records, 1e-20, false).is_err());
+    }
+}
@@ -0,0 +1,327 @@
+// Script Engine Module for TermiPro
+// Rhai-based automation for send/expect sequences over serial, TCP and Modbus
+
+use crate::modbus::ModbusRequest;
+use crate::recorder::RecorderState;
+use crate::{find_subsequence, get_timestamp, ModbusState, SerialConfig, SerialState, TcpState};
+use parking_lot::Mutex;
+use regex::bytes::Regex;
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+// ===================== CONSTANTS =====================
+
+/// Max bytes buffered per connection while scripts are running
+const MAX_INPUT_BUFFER: usize = 64 * 1024;
+
+/// Poll interval for expect / sleep (ms)
+const POLL_INTERVAL_MS: u64 = 10;
+
+// ===================== STRUCTS =====================
+
+/// Script log event
+#[derive(Debug, Serialize, Clone)]
+pub struct ScriptLog {
+    pub script_id: String,
+    pub message: String,
+    pub timestamp: u64,
+}
+
+/// Script status event
+#[derive(Debug, Serialize, Clone)]
+pub struct ScriptStatus {
+    pub script_id: String,
+    pub status: String, // "running", "finished", "stopped", "error"
+    pub message: Option<String>,
+}
+
+/// State for managing running scripts and the data they wait on
+#[derive(Default)]
+pub struct ScriptState {
+    pub running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+    /// Received data per port name / TCP connection ID, consumed by expect
+    inputs: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl ScriptState {
+    /// Buffer received data (only while at least one script is running)
+    pub fn feed(&self, source_id: &str, data: &[u8]) {
+        if self.running.lock().is_empty() {
+            return;
+        }
+
+        let mut inputs = self.inputs.lock();
+        let buffer = inputs.entry(source_id.to_string()).or_default();
+        buffer.extend_from_slice(data);
+        if buffer.len() > MAX_INPUT_BUFFER {
+            let excess = buffer.len() - MAX_INPUT_BUFFER;
+            buffer.drain(..excess);
+        }
+    }
+
+    /// Drop all buffered data (called when the last script ends)
+    pub fn clear_all_inputs(&self) {
+        self.inputs.lock().clear();
+    }
+
+    /// Drop buffered data for a source
+    pub fn clear_input(&self, source_id: &str) {
+        self.inputs.lock().remove(source_id);
+    }
+
+    /// Take the first regex match, consuming the buffer up to the end of the match
+    pub fn take_match(&self, source_id: &str, pattern: &Regex) -> Option<Vec<u8>> {
+        let mut inputs = self.inputs.lock();
+        let buffer = inputs.get_mut(source_id)?;
+        let (start, end) = pattern.find(buffer).map(|m| (m.start(), m.end()))?;
+        let matched = buffer[start..end].to_vec();
+        buffer.drain(..end);
+        Some(matched)
+    }
+
+    /// Take the first occurrence of a byte pattern, consuming the buffer up to its end
+    pub fn take_bytes(&self, source_id: &str, pattern: &[u8]) -> bool {
+        let mut inputs = self.inputs.lock();
+        let Some(buffer) = inputs.get_mut(source_id) else {
+            return false;
+        };
+        match find_subsequence(buffer, pattern) {
+            Some(pos) => {
+                buffer.drain(..pos + pattern.len());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// ===================== HELPER FUNCTIONS =====================
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn parse_hex(hex: &str) -> Vec<u8> {
+    let hex_chars: String = hex.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+    hex_chars
+        .as_bytes()
+        .chunks(2)
+        .map(|chunk| u8::from_str_radix(std::str::from_utf8(chunk).unwrap_or("00"), 16).unwrap_or(0))
+        .collect()
+}
+
+/// Wait until `check` succeeds, the timeout expires or the script is stopped
+fn wait_for<T>(
+    stop: &AtomicBool,
+    timeout_ms: i64,
+    mut check: impl FnMut() -> Option<T>,
+) -> Result<Option<T>, Box<EvalAltResult>> {
+    let deadline = Instant::now() + Duration::from_millis(timeout_ms.max(0) as u64);
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return Err("Script stopped".into());
+        }
+        if let Some(found) = check() {
+            return Ok(Some(found));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+fn emit_log(app: &AppHandle, script_id: &str, message: &str) {
+    let _ = app.emit(
+        "script-log",
+        ScriptLog {
+            script_id: script_id.to_string(),
+            message: message.to_string(),
+            timestamp: get_timestamp(),
+        },
+    );
+}
+
+fn modbus_call(app: &AppHandle, request: ModbusRequest) -> Result<Array, Box<EvalAltResult>> {
+    let response = crate::modbus_request(app.clone(), app.state::<ModbusState>(), request)?;
+    if !response.success {
+        return Err(response
+            .error_message
+            .unwrap_or_else(|| "Modbus request failed".to_string())
+            .into());
+    }
+
+    let values: Array = match (response.data, response.coils) {
+        (Some(data), _) => data.into_iter().map(|v| Dynamic::from_int(v as i64)).collect(),
+        (None, Some(coils)) => coils.into_iter().map(Dynamic::from_bool).collect(),
+        (None, None) => Array::new(),
+    };
+    Ok(values)
+}
+
+// ===================== ENGINE =====================
+
+/// Build a Rhai engine with the TermiPro API bound to `app`.
+///
+/// Serial: open_port(port, baud), close_port(port), send(port, text), send_hex(port, hex)
+/// TCP client: tcp_send(id, text), tcp_send_hex(id, hex)
+/// Receive: expect(id, regex, timeout_ms) -> matched text or (), expect_bytes(id, hex, timeout_ms) -> bool,
+///          clear_input(id)
+/// Modbus: modbus_read(id, fc, address, quantity) -> array, modbus_write(id, fc, address, values)
+/// Misc: sleep(ms), log(message), print(message)
+pub fn create_engine(app: AppHandle, script_id: String, stop: Arc<AtomicBool>) -> Engine {
+    let mut engine = Engine::new();
+
+    let stop_flag = stop.clone();
+    engine.on_progress(move |_| stop_flag.load(Ordering::SeqCst).then_some(Dynamic::UNIT));
+
+    let (a, id) = (app.clone(), script_id.clone());
+    engine.on_print(move |msg| emit_log(&a, &id, msg));
+    let (a, id) = (app.clone(), script_id.clone());
+    engine.on_debug(move |msg, _, _| emit_log(&a, &id, msg));
+    let (a, id) = (app.clone(), script_id.clone());
+    engine.register_fn("log", move |msg: &str| emit_log(&a, &id, msg));
+
+    let s = stop.clone();
+    engine.register_fn("sleep", move |ms: i64| -> Result<(), Box<EvalAltResult>> {
+        wait_for(&s, ms, || None::<()>).map(|_| ())
+    });
+
+    // Serial
+    let a = app.clone();
+    engine.register_fn("open_port", move |port: &str, baud: i64| -> Result<(), Box<EvalAltResult>> {
+        let config = SerialConfig {
+            port_name: port.to_string(),
+            baud_rate: baud as u32,
+            data_bits: 8,
+            stop_bits: "1".to_string(),
+            parity: "none".to_string(),
+            dtr: true,
+            rts: true,
+            line_ending: None,
+            flow_control: "none".to_string(),
+        };
+        crate::open_serial_port(&a, &a.state::<SerialState>(), config)?;
+        Ok(())
+    });
+    let a = app.clone();
+    engine.register_fn("close_port", move |port: &str| -> Result<(), Box<EvalAltResult>> {
+        crate::close_port(a.state::<SerialState>(), port.to_string())?;
+        Ok(())
+    });
+    let a = app.clone();
+    engine.register_fn("send", move |port: &str, text: &str| -> Result<(), Box<EvalAltResult>> {
+        crate::send_data(a.state::<SerialState>(), a.state::<RecorderState>(), port.to_string(), text.to_string(), false, None)?;
+        Ok(())
+    });
+    let a = app.clone();
+    engine.register_fn("send_hex", move |port: &str, hex: &str| -> Result<(), Box<EvalAltResult>> {
+        crate::send_data(a.state::<SerialState>(), a.state::<RecorderState>(), port.to_string(), hex.to_string(), true, None)?;
+        Ok(())
+    });
+
+    // TCP client
+    let a = app.clone();
+    engine.register_fn("tcp_send", move |conn: &str, text: &str| -> Result<(), Box<EvalAltResult>> {
+        crate::tcp_client_send(a.state::<TcpState>(), a.state::<RecorderState>(), conn.to_string(), text.to_string(), false)?;
+        Ok(())
+    });
+    let a = app.clone();
+    engine.register_fn("tcp_send_hex", move |conn: &str, hex: &str| -> Result<(), Box<EvalAltResult>> {
+        crate::tcp_client_send(a.state::<TcpState>(), a.state::<RecorderState>(), conn.to_string(), hex.to_string(), true)?;
+        Ok(())
+    });
+
+    // Receive
+    let (a, s) = (app.clone(), stop.clone());
+    engine.register_fn("expect", move |source: &str, pattern: &str, timeout_ms: i64| -> Result<Dynamic, Box<EvalAltResult>> {
+        let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+        let state = a.state::<ScriptState>();
+        let found = wait_for(&s, timeout_ms, || state.take_match(source, &regex))?;
+        Ok(found
+            .map(|m| Dynamic::from(String::from_utf8_lossy(&m).to_string()))
+            .unwrap_or(Dynamic::UNIT))
+    });
+    let (a, s) = (app.clone(), stop.clone());
+    engine.register_fn("expect_bytes", move |source: &str, hex: &str, timeout_ms: i64| -> Result<bool, Box<EvalAltResult>> {
+        let pattern = parse_hex(hex);
+        if pattern.is_empty() {
+            return Err("Empty byte pattern".into());
+        }
+        let state = a.state::<ScriptState>();
+        let found = wait_for(&s, timeout_ms, || state.take_bytes(source, &pattern).then_some(()))?;
+        Ok(found.is_some())
+    });
+    let a = app.clone();
+    engine.register_fn("clear_input", move |source: &str| {
+        a.state::<ScriptState>().clear_input(source);
+    });
+    engine.register_fn("to_hex", |text: &str| to_hex(text.as_bytes()));
+
+    // Modbus
+    let a = app.clone();
+    engine.register_fn("modbus_read", move |conn: &str, function_code: i64, address: i64, quantity: i64| -> Result<Array, Box<EvalAltResult>> {
+        modbus_call(&a, ModbusRequest {
+            connection_id: conn.to_string(),
+            function_code: function_code as u8,
+            start_address: address as u16,
+            quantity: quantity as u16,
+            values: None,
+            coil_values: None,
+            ..Default::default()
+        })
+    });
+    engine.register_fn("modbus_write", move |conn: &str, function_code: i64, address: i64, values: Array| -> Result<(), Box<EvalAltResult>> {
+        let is_coil = matches!(function_code, 0x05 | 0x0F);
+        let (registers, coils) = if is_coil {
+            (None, Some(values.iter().map(|v| v.as_bool().unwrap_or_else(|_| v.as_int().unwrap_or(0) != 0)).collect()))
+        } else {
+            (Some(values.iter().map(|v| v.as_int().unwrap_or(0) as u16).collect()), None)
+        };
+        modbus_call(&app, ModbusRequest {
+            connection_id: conn.to_string(),
+            function_code: function_code as u8,
+            start_address: address as u16,
+            quantity: values.len() as u16,
+            values: registers,
+            coil_values: coils,
+            ..Default::default()
+        })?;
+        Ok(())
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_match_consumes_input() {
+        let state = ScriptState::default();
+        state.running.lock().insert("s1".to_string(), Arc::new(AtomicBool::new(true)));
+
+        state.feed("COM1", b"AT\r\n+CSQ: 23,0\r\nOK\r\n");
+        state.feed("COM2", b"ignored");
+
+        let regex = Regex::new(r"\+CSQ: (\d+)").unwrap();
+        assert_eq!(state.take_match("COM1", &regex), Some(b"+CSQ: 23".to_vec()));
+        assert_eq!(state.take_match("COM1", &regex), None);
+        assert!(state.take_bytes("COM1", b"OK\r\n"));
+        assert!(!state.take_bytes("COM1", b"OK"));
+    }
+
+    #[test]
+    fn test_feed_ignored_without_running_script() {
+        let state = ScriptState::default();
+        state.feed("COM1", b"OK");
+        assert!(!state.take_bytes("COM1", b"OK"));
+    }
+}
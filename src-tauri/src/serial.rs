@@ -0,0 +1,575 @@
+// Serial Utilities Module for TermiPro
+// Helpers for baud rate detection and port discovery
+
+use crate::modbus::verify_crc16;
+use serde::{Deserialize, Serialize};
+use serialport::{DataBits, Parity, SerialPort, StopBits};
+use std::io;
+use std::time::{Duration, Instant};
+
+// ===================== CONSTANTS =====================
+
+/// Standard baud rates tried by auto-detection (ascending)
+pub const STANDARD_BAUD_RATES: [u32; 11] = [
+    1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600,
+];
+
+/// Default sampling time per baud rate (ms)
+const DEFAULT_SAMPLE_MS: u64 = 300;
+
+// ===================== CONFIG STRUCTS =====================
+
+/// Baud rate auto-detection configuration
+#[derive(Debug, Deserialize, Clone)]
+pub struct BaudDetectConfig {
+    pub port_name: String,
+    /// Rates to try (defaults to STANDARD_BAUD_RATES)
+    #[serde(default)]
+    pub baud_rates: Option<Vec<u32>>,
+    #[serde(default = "default_data_bits")]
+    pub data_bits: u8,
+    #[serde(default = "default_stop_bits")]
+    pub stop_bits: String,
+    #[serde(default = "default_parity")]
+    pub parity: String,
+    /// Listening time per baud rate (ms)
+    #[serde(default = "default_sample_ms")]
+    pub sample_ms: u64,
+    /// Optional bytes sent after opening at each rate (e.g. "AT\r")
+    #[serde(default)]
+    pub probe: Option<Vec<u8>>,
+}
+
+impl BaudDetectConfig {
+    /// Baud rates to try: the requested list (non-empty, no zero rate) or STANDARD_BAUD_RATES
+    pub fn candidate_baud_rates(&self) -> Result<Vec<u32>, String> {
+        match &self.baud_rates {
+            None => Ok(STANDARD_BAUD_RATES.to_vec()),
+            Some(rates) if rates.is_empty() => Err("Baud rate list is empty".to_string()),
+            Some(rates) if rates.contains(&0) => Err("Baud rate must be greater than 0".to_string()),
+            Some(rates) => Ok(rates.clone()),
+        }
+    }
+}
+
+fn default_data_bits() -> u8 {
+    8
+}
+
+fn default_stop_bits() -> String {
+    "1".to_string()
+}
+
+fn default_parity() -> String {
+    "none".to_string()
+}
+
+fn default_sample_ms() -> u64 {
+    DEFAULT_SAMPLE_MS
+}
+
+/// Score of one baud rate candidate
+#[derive(Debug, Serialize, Clone)]
+pub struct BaudCandidate {
+    pub baud_rate: u32,
+    /// Overall score (0.0 - 1.0)
+    pub score: f64,
+    pub bytes_received: usize,
+    /// Ratio of printable ASCII bytes (incl. CR, LF, TAB)
+    pub printable_ratio: f64,
+    pub line_endings: usize,
+    /// Chunks that are valid Modbus RTU frames (CRC ok)
+    pub modbus_frames: usize,
+}
+
+/// Filter options for list_serial_ports
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PortListOptions {
+    /// List every port reported by the OS (no USB / name filter)
+    #[serde(default)]
+    pub include_all: bool,
+    /// Also list pseudo-terminals, rfcomm and socat links
+    #[serde(default)]
+    pub include_virtual: bool,
+    /// Only keep ports matching this glob (`*`, `?`), e.g. "/dev/ttyUSB*" or "/tmp/ttyV*"
+    #[serde(default)]
+    pub name_glob: Option<String>,
+}
+
+// ===================== PORT DISCOVERY =====================
+
+/// Match a name against a simple glob pattern (`*` = any run, `?` = one char)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// Check if a device name looks like a virtual / non-enumerated serial port
+pub fn is_virtual_port_name(name: &str) -> bool {
+    let is_numbered = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+    };
+
+    is_numbered("/dev/pts/") || is_numbered("/dev/rfcomm") || is_numbered("/dev/tnt")
+}
+
+/// Find virtual ports on disk: /dev/pts/N, /dev/rfcommN, /dev/tntN (tty0tty)
+/// and, for an absolute glob such as "/tmp/ttyV*", matching entries in its directory
+#[cfg(unix)]
+pub fn scan_virtual_ports(name_glob: Option<&str>) -> Vec<String> {
+    let mut found = Vec::new();
+
+    let mut scan_dir = |dir: &str, keep: &dyn Fn(&str) -> bool| {
+        if let Ok(entries) = std::fs::read_dir(dir) {
+            for entry in entries.flatten() {
+                let path = entry.path().to_string_lossy().to_string();
+                if keep(&path) && !found.contains(&path) {
+                    found.push(path);
+                }
+            }
+        }
+    };
+
+    scan_dir("/dev/pts", &is_virtual_port_name);
+    scan_dir("/dev", &is_virtual_port_name);
+
+    if let Some(glob) = name_glob.filter(|g| g.starts_with('/')) {
+        if let Some(idx) = glob.rfind('/') {
+            let dir = if idx == 0 { "/" } else { &glob[..idx] };
+            if !dir.contains(['*', '?']) {
+                scan_dir(dir, &|path: &str| glob_match(glob, path));
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+#[cfg(not(unix))]
+pub fn scan_virtual_ports(_name_glob: Option<&str>) -> Vec<String> {
+    Vec::new()
+}
+
+// ===================== SCORING =====================
+
+/// Score the bytes received at one baud rate.
+/// `chunks` are the data bursts separated by idle gaps.
+pub fn score_baud_sample(baud_rate: u32, chunks: &[Vec<u8>]) -> BaudCandidate {
+    let bytes_received: usize = chunks.iter().map(|c| c.len()).sum();
+
+    if bytes_received == 0 {
+        return BaudCandidate {
+            baud_rate,
+            score: 0.0,
+            bytes_received: 0,
+            printable_ratio: 0.0,
+            line_endings: 0,
+            modbus_frames: 0,
+        };
+    }
+
+    let printable = chunks
+        .iter()
+        .flatten()
+        .filter(|&&b| (0x20..=0x7E).contains(&b) || b == b'\r' || b == b'\n' || b == b'\t')
+        .count();
+    let printable_ratio = printable as f64 / bytes_received as f64;
+
+    let line_endings = chunks.iter().flatten().filter(|&&b| b == b'\n').count();
+
+    let modbus_frames = chunks
+        .iter()
+        .filter(|c| c.len() >= 4 && verify_crc16(c))
+        .count();
+
+    // Text: printable ratio, small bonus for proper line endings
+    let text_score = if line_endings > 0 {
+        (printable_ratio + 0.1).min(1.0)
+    } else {
+        printable_ratio * 0.9
+    };
+
+    // Binary Modbus: ratio of bursts with a valid CRC
+    let modbus_score = modbus_frames as f64 / chunks.iter().filter(|c| !c.is_empty()).count() as f64;
+
+    BaudCandidate {
+        baud_rate,
+        score: text_score.max(modbus_score),
+        bytes_received,
+        printable_ratio,
+        line_endings,
+        modbus_frames,
+    }
+}
+
+/// Sort candidates best first (score, then bytes received)
+pub fn rank_candidates(candidates: &mut [BaudCandidate]) {
+    candidates.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.bytes_received.cmp(&a.bytes_received))
+    });
+}
+
+// ===================== DEADLINE READS =====================
+
+/// Serial port whose read timeout is capped by a deadline, so a request timeout shorter
+/// than the connect-time port timeout is honoured. The original timeout is restored on drop.
+pub struct DeadlineReader<'a> {
+    port: &'a mut dyn SerialPort,
+    previous_timeout: Duration,
+    current_timeout: Duration,
+    deadline: Instant,
+}
+
+impl<'a> DeadlineReader<'a> {
+    pub fn new(port: &'a mut dyn SerialPort, deadline: Instant) -> Self {
+        let previous_timeout = port.timeout();
+        Self {
+            port,
+            previous_timeout,
+            current_timeout: previous_timeout,
+            deadline,
+        }
+    }
+
+    /// Read with timeout = min(port timeout, time left); TimedOut once the deadline has passed
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        let timeout = remaining.min(self.previous_timeout).max(Duration::from_millis(1));
+        if timeout != self.current_timeout {
+            self.port.set_timeout(timeout)?;
+            self.current_timeout = timeout;
+        }
+        self.port.read(buf)
+    }
+}
+
+impl Drop for DeadlineReader<'_> {
+    fn drop(&mut self) {
+        if self.current_timeout != self.previous_timeout {
+            let _ = self.port.set_timeout(self.previous_timeout);
+        }
+    }
+}
+
+// ===================== LINE SETTINGS =====================
+
+/// Baud rate and framing of an open port
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineSettings {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub stop_bits: StopBits,
+    pub parity: Parity,
+}
+
+impl LineSettings {
+    /// Settings currently in effect on a port
+    pub fn read(port: &dyn SerialPort) -> Result<Self, String> {
+        let error = |e: serialport::Error| format!("Read port settings error: {}", e);
+        Ok(Self {
+            baud_rate: port.baud_rate().map_err(error)?,
+            data_bits: port.data_bits().map_err(error)?,
+            stop_bits: port.stop_bits().map_err(error)?,
+            parity: port.parity().map_err(error)?,
+        })
+    }
+
+    fn apply(&self, port: &mut dyn SerialPort) -> Result<(), String> {
+        port.set_baud_rate(self.baud_rate)
+            .map_err(|e| format!("Set baud rate error: {}", e))?;
+        port.set_data_bits(self.data_bits)
+            .map_err(|e| format!("Set data bits error: {}", e))?;
+        port.set_stop_bits(self.stop_bits)
+            .map_err(|e| format!("Set stop bits error: {}", e))?;
+        port.set_parity(self.parity)
+            .map_err(|e| format!("Set parity error: {}", e))
+    }
+}
+
+/// Change the settings of an open port. When one setting is refused the previous
+/// settings are restored, so the port never runs with half of the new configuration.
+pub fn reconfigure_line(port: &mut dyn SerialPort, settings: LineSettings) -> Result<(), String> {
+    let previous = LineSettings::read(port)?;
+    if let Err(e) = settings.apply(port) {
+        let _ = previous.apply(port);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// In-memory serial port for tests: scripted reads, recorded writes and settings
+#[cfg(test)]
+pub mod mock {
+    use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+    use std::collections::VecDeque;
+    use std::io;
+    use std::time::Duration;
+
+    pub struct MockSerialPort {
+        /// Chunks returned by successive reads; reads time out once empty
+        pub reads: VecDeque<Vec<u8>>,
+        pub written: Vec<u8>,
+        pub baud_rate: u32,
+        pub parity: Parity,
+        pub timeout: Duration,
+        /// Timeout in effect for each read call
+        pub read_timeouts: Vec<Duration>,
+        /// Parity changes fail (e.g. unsupported by the driver)
+        pub reject_parity: bool,
+    }
+
+    impl MockSerialPort {
+        pub fn new(timeout: Duration) -> Self {
+            Self {
+                reads: VecDeque::new(),
+                written: Vec::new(),
+                baud_rate: 9600,
+                parity: Parity::None,
+                timeout,
+                read_timeouts: Vec::new(),
+                reject_parity: false,
+            }
+        }
+    }
+
+    impl io::Read for MockSerialPort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.read_timeouts.push(self.timeout);
+            let Some(mut chunk) = self.reads.pop_front() else {
+                std::thread::sleep(self.timeout);
+                return Err(io::ErrorKind::TimedOut.into());
+            };
+            let n = chunk.len().min(buf.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            if n < chunk.len() {
+                self.reads.push_front(chunk.split_off(n));
+            }
+            Ok(n)
+        }
+    }
+
+    impl io::Write for MockSerialPort {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SerialPort for MockSerialPort {
+        fn name(&self) -> Option<String> {
+            Some("mock".to_string())
+        }
+        fn baud_rate(&self) -> serialport::Result<u32> {
+            Ok(self.baud_rate)
+        }
+        fn data_bits(&self) -> serialport::Result<DataBits> {
+            Ok(DataBits::Eight)
+        }
+        fn flow_control(&self) -> serialport::Result<FlowControl> {
+            Ok(FlowControl::None)
+        }
+        fn parity(&self) -> serialport::Result<Parity> {
+            Ok(self.parity)
+        }
+        fn stop_bits(&self) -> serialport::Result<StopBits> {
+            Ok(StopBits::One)
+        }
+        fn timeout(&self) -> Duration {
+            self.timeout
+        }
+        fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+            self.baud_rate = baud_rate;
+            Ok(())
+        }
+        fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_parity(&mut self, parity: Parity) -> serialport::Result<()> {
+            if self.reject_parity {
+                return Err(serialport::Error::new(serialport::ErrorKind::InvalidInput, "parity not supported"));
+            }
+            self.parity = parity;
+            Ok(())
+        }
+        fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn set_timeout(&mut self, timeout: Duration) -> serialport::Result<()> {
+            self.timeout = timeout;
+            Ok(())
+        }
+        fn write_request_to_send(&mut self, _: bool) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn write_data_terminal_ready(&mut self, _: bool) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+            Ok(false)
+        }
+        fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+            Ok(false)
+        }
+        fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+            Ok(false)
+        }
+        fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+            Ok(false)
+        }
+        fn bytes_to_read(&self) -> serialport::Result<u32> {
+            Ok(self.reads.iter().map(|c| c.len() as u32).sum())
+        }
+        fn bytes_to_write(&self) -> serialport::Result<u32> {
+            Ok(0)
+        }
+        fn clear(&self, _: ClearBuffer) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+            Err(serialport::Error::new(serialport::ErrorKind::Unknown, "mock port cannot be cloned"))
+        }
+        fn set_break(&self) -> serialport::Result<()> {
+            Ok(())
+        }
+        fn clear_break(&self) -> serialport::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("/dev/ttyUSB*", "/dev/ttyUSB0"));
+        assert!(glob_match("COM?", "COM3"));
+        assert!(glob_match("*rfcomm*", "/dev/rfcomm0"));
+        assert!(!glob_match("COM?", "COM12"));
+        assert!(!glob_match("/dev/ttyACM*", "/dev/ttyUSB0"));
+    }
+
+    #[test]
+    fn test_is_virtual_port_name() {
+        assert!(is_virtual_port_name("/dev/pts/3"));
+        assert!(is_virtual_port_name("/dev/rfcomm0"));
+        assert!(!is_virtual_port_name("/dev/pts/ptmx"));
+        assert!(!is_virtual_port_name("/dev/ttyUSB0"));
+    }
+
+    #[test]
+    fn test_score_text_sample() {
+        let chunks = vec![b"OK\r\n".to_vec(), b"+CSQ: 23,0\r\n".to_vec()];
+        let candidate = score_baud_sample(115200, &chunks);
+        assert_eq!(candidate.line_endings, 2);
+        assert!((candidate.score - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_baud_detect_candidates() {
+        let mut config: BaudDetectConfig =
+            serde_json::from_str(r#"{"port_name": "/dev/ttyUSB0"}"#).unwrap();
+        assert_eq!(config.candidate_baud_rates().unwrap(), STANDARD_BAUD_RATES.to_vec());
+
+        config.baud_rates = Some(vec![9600, 0]);
+        assert!(config.candidate_baud_rates().is_err());
+        config.baud_rates = Some(Vec::new());
+        assert!(config.candidate_baud_rates().is_err());
+        config.baud_rates = Some(vec![19200]);
+        assert_eq!(config.candidate_baud_rates().unwrap(), vec![19200]);
+    }
+
+    #[test]
+    fn test_score_modbus_sample_and_rank() {
+        let frame = vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD];
+        let garbage = vec![vec![0xF8, 0x00, 0xFF, 0x80, 0x00]];
+
+        let mut candidates = vec![
+            score_baud_sample(4800, &garbage),
+            score_baud_sample(9600, &[frame.clone(), frame]),
+            score_baud_sample(19200, &[]),
+        ];
+        rank_candidates(&mut candidates);
+
+        assert_eq!(candidates[0].baud_rate, 9600);
+        assert_eq!(candidates[0].modbus_frames, 2);
+        assert_eq!(candidates[2].baud_rate, 19200);
+    }
+
+    #[test]
+    fn test_deadline_reader_caps_port_timeout() {
+        let mut port = mock::MockSerialPort::new(Duration::from_millis(1000));
+        let start = Instant::now();
+        {
+            let mut reader = DeadlineReader::new(&mut port, start + Duration::from_millis(30));
+            let mut buf = [0u8; 16];
+            // Keep polling like the request loop does until the deadline passes
+            for _ in 0..100 {
+                let _ = reader.read(&mut buf);
+            }
+        }
+
+        // A silent device costs the probe timeout, not the 1 s connect-time timeout
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(port.read_timeouts.iter().all(|&t| t <= Duration::from_millis(30)));
+        assert_eq!(port.timeout, Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_reconfigure_line_rolls_back() {
+        let mut port = mock::MockSerialPort::new(Duration::from_millis(10));
+        port.baud_rate = 9600;
+        let settings = LineSettings {
+            baud_rate: 19200,
+            data_bits: DataBits::Eight,
+            stop_bits: StopBits::One,
+            parity: Parity::Even,
+        };
+
+        reconfigure_line(&mut port, settings).unwrap();
+        assert_eq!((port.baud_rate, port.parity), (19200, Parity::Even));
+
+        // Parity refused by the driver: the baud rate change is undone
+        port.reject_parity = true;
+        let result = reconfigure_line(&mut port, LineSettings { baud_rate: 38400, ..settings });
+        assert!(result.is_err());
+        assert_eq!(port.baud_rate, 19200);
+    }
+}